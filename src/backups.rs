@@ -0,0 +1,129 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eyre::{eyre, Result};
+
+use crate::install_lock::{self, LOCK_FILE};
+
+// a backup is still being written while it has this suffix, one left behind by a crash is
+// incomplete and never counts towards the retention
+const PARTIAL_SUFFIX: &str = ".partial";
+
+// backups of an install live in the data directory, one directory per patch it was taken at
+pub fn backups_dir(data_dir: &Path, app: &str) -> PathBuf {
+    data_dir.join("backups").join(app)
+}
+
+fn copy_dir(from: &Path, to: &Path, copied: &mut dyn FnMut(u64)) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for dir_entry in fs::read_dir(from)? {
+        let dir_entry = dir_entry?;
+        let file_name = dir_entry.file_name();
+        let metadata = dir_entry.metadata()?;
+
+        // the lock belongs to the install, a restored backup mustn't bring ours back
+        if file_name == LOCK_FILE {
+            continue;
+        }
+
+        if metadata.is_dir() {
+            copy_dir(&dir_entry.path(), &to.join(&file_name), copied)?;
+        } else if metadata.is_file() {
+            copied(fs::copy(dir_entry.path(), to.join(&file_name))?);
+        }
+    }
+
+    Ok(())
+}
+
+// copies the install as it is at `patch`, butler patches in place so this is the only
+// way back should the update turn out bad, see restore. `copied` hears about every file as it's done, a
+// large install takes a while
+pub fn create(
+    install_dir: &Path,
+    backups_dir: &Path,
+    patch: u16,
+    copied: &mut dyn FnMut(u64),
+) -> io::Result<PathBuf> {
+    let backup_dir = backups_dir.join(patch.to_string());
+    let partial_dir = backups_dir.join(format!("{}{}", patch, PARTIAL_SUFFIX));
+
+    let _ = fs::remove_dir_all(&partial_dir);
+    if let Err(e) = copy_dir(install_dir, &partial_dir, copied) {
+        let _ = fs::remove_dir_all(&partial_dir);
+        return Err(e);
+    }
+
+    // a backup of the same patch from an earlier, failed update is no better than this one
+    let _ = fs::remove_dir_all(&backup_dir);
+    fs::rename(&partial_dir, &backup_dir)?;
+    Ok(backup_dir)
+}
+
+// patches there are complete backups of, oldest first
+pub fn list(backups_dir: &Path) -> Vec<u16> {
+    let mut patches: Vec<u16> = fs::read_dir(backups_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|dir_entry| dir_entry.path().is_dir())
+        .filter_map(|dir_entry| dir_entry.file_name().to_str()?.parse().ok())
+        .collect();
+    patches.sort_unstable();
+    patches
+}
+
+// removes all but the newest `keep` backups, along with anything a crash left half written
+pub fn prune(backups_dir: &Path, keep: usize) {
+    let backups = list(backups_dir);
+    let excess = backups.len().saturating_sub(keep);
+    let stale = backups[..excess]
+        .iter()
+        .map(|patch| backups_dir.join(patch.to_string()));
+    let partial = fs::read_dir(backups_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|dir_entry| dir_entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(PARTIAL_SUFFIX));
+
+    for backup_dir in stale.chain(partial).collect::<Vec<_>>() {
+        log::info!("removing old backup {}", backup_dir.display());
+        if let Err(e) = fs::remove_dir_all(&backup_dir) {
+            log::warn!("could not remove {}: {}", backup_dir.display(), e);
+        }
+    }
+}
+
+// puts the newest backup back in place of the install, whatever the update changed and
+// anything else added since goes. returns the patch the backup was taken at
+pub fn restore(backups_dir: &Path, install_dir: &Path) -> Result<u16> {
+    let patch = *list(backups_dir)
+        .last()
+        .ok_or_else(|| eyre!("There is no backup of the game to go back to."))?;
+    let backup_dir = backups_dir.join(patch.to_string());
+
+    // held throughout so the game can't start on a half restored install
+    let _install_lock = install_lock::acquire(install_dir)?;
+    log::info!(
+        "restoring {} from {}",
+        install_dir.display(),
+        backup_dir.display()
+    );
+    for dir_entry in fs::read_dir(install_dir)? {
+        let dir_entry = dir_entry?;
+        if dir_entry.file_name() == LOCK_FILE {
+            continue;
+        }
+        if dir_entry.file_type()?.is_dir() {
+            fs::remove_dir_all(dir_entry.path())?;
+        } else {
+            fs::remove_file(dir_entry.path())?;
+        }
+    }
+    // a restore that stops halfway can be run again, the backup itself is left untouched
+    copy_dir(&backup_dir, install_dir, &mut |_| {})?;
+
+    Ok(patch)
+}
@@ -0,0 +1,264 @@
+use std::fs;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::paths;
+
+pub const BUTLER_PATH: &str = "tools/butler";
+
+// oldest butler release whose `apply` flags and semantics we rely on
+const MIN_BUTLER_VERSION: &str = "15.20.0";
+
+// SHA-256 of the butler build shipped with this release, set by the packaging script
+// whenever the bundled tool is bumped, e.g. BUTLER_SHA256=$(sha256sum tools/butler.exe).
+// builds without it don't compile, so no launcher ever runs a butler it can't check
+const BUTLER_SHA256: &str = env!("BUTLER_SHA256");
+
+// keeps butler from flashing a console window on Windows
+pub const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+lazy_static! {
+    // butler is only checked once per session, the bundled tool can't change underneath us
+    static ref BUTLER_INTEGRITY: Result<(), String> = check_integrity().map_err(|e| e.to_string());
+    static ref BUTLER_VERSION: Result<Version, String> = query_version().map_err(|e| e.to_string());
+}
+
+// the file on disk behind BUTLER_PATH, Windows resolves the extension on its own when spawning
+fn binary_path() -> PathBuf {
+    let exe_path = Path::new(BUTLER_PATH).with_extension("exe");
+    if exe_path.exists() {
+        exe_path
+    } else {
+        PathBuf::from(BUTLER_PATH)
+    }
+}
+
+fn check_integrity() -> Result<()> {
+    // sha256sum puts the file name after the hash
+    let expected = BUTLER_SHA256
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        log::error!(
+            "the embedded butler hash {:?} is not a SHA-256",
+            BUTLER_SHA256
+        );
+        return Err(eyre!(
+            "The patching tool can't be checked, this build of the AppLauncher is broken."
+        ));
+    }
+
+    let butler_binary = fs::read(binary_path())
+        .map_err(|e| eyre!("The patching tool could not be read ({}).", e))?;
+    let actual = format!("{:x}", Sha256::digest(butler_binary.as_slice()));
+
+    if actual != expected {
+        log::error!(
+            "butler hash mismatch, expected: {}, found: {}",
+            expected,
+            actual
+        );
+        return Err(eyre!(
+            "The patching tool has been modified or is corrupted. Please reinstall the AppLauncher."
+        ));
+    }
+
+    Ok(())
+}
+
+fn query_version() -> Result<Version> {
+    let mut cmd = process::Command::new(BUTLER_PATH);
+    cmd.arg("--version")
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped());
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd
+        .output()
+        .map_err(|e| eyre!("The patching tool could not be started ({}).", e))?;
+
+    // depending on the build, butler prints its version to either stream
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let raw = if stdout.trim().is_empty() {
+        stderr.trim()
+    } else {
+        stdout.trim()
+    };
+
+    parse_version(raw).ok_or_else(|| {
+        eyre!(
+            "The patching tool reported an unrecognized version ({}).",
+            raw
+        )
+    })
+}
+
+// butler reports itself as e.g. "v15.21.0, built on Oct 14 2020 @ 10:52:27, ref 3ec1e7a"
+fn parse_version(raw: &str) -> Option<Version> {
+    let first = raw.split(',').next()?.trim();
+    Version::parse(first.strip_prefix("v").unwrap_or(first)).ok()
+}
+
+// version of the bundled butler, queried on first use
+pub fn version() -> Result<Version> {
+    BUTLER_VERSION.clone().map_err(|e| eyre!(e))
+}
+
+// fails if the bundled butler is missing, tampered with, or older than what we support
+pub fn ensure_supported() -> Result<()> {
+    // never run a butler we can't vouch for, not even for --version
+    BUTLER_INTEGRITY.clone().map_err(|e| eyre!(e))?;
+
+    let version = version()?;
+    let minimum = Version::parse(MIN_BUTLER_VERSION)?;
+
+    if version < minimum {
+        return Err(eyre!(
+            "The patching tool is too old (found v{}, need v{} or newer). Please reinstall the AppLauncher.",
+            version,
+            minimum
+        ));
+    }
+
+    Ok(())
+}
+
+// checks that a directory matches a signature exactly, without modifying anything
+pub fn verify(signature: &Path, dir: &Path) -> Result<()> {
+    let mut cmd = process::Command::new(BUTLER_PATH);
+    cmd.arg("verify")
+        .arg(paths::long_path_safe(signature))
+        .arg(paths::long_path_safe(dir))
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let status = cmd.status()?;
+    if !status.success() {
+        return Err(eyre!("butler verify exited with {}", status));
+    }
+
+    Ok(())
+}
+
+// patches a directory in place, streaming --json progress on stdout, the caller decides
+// what happens to the standard streams
+pub fn apply_command(
+    patch: &Path,
+    dir: &Path,
+    signature: Option<&Path>,
+    staging_dir: &Path,
+) -> process::Command {
+    let mut cmd = process::Command::new(BUTLER_PATH);
+    cmd.arg("--json")
+        .arg("apply")
+        .arg("--staging-dir")
+        .arg(paths::long_path_safe(staging_dir))
+        .arg(paths::long_path_safe(patch))
+        .arg(paths::long_path_safe(dir));
+    if let Some(signature) = signature {
+        cmd.arg("--signature").arg(paths::long_path_safe(signature));
+    }
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    cmd
+}
+
+// with --json, butler writes one of these per line to stdout
+#[derive(Debug, Deserialize)]
+struct JsonMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    progress: Option<f64>,
+    percentage: Option<f64>,
+    level: Option<String>,
+    message: Option<String>,
+}
+
+pub enum Event {
+    // how far along the operation is, from 0.0 to 1.0
+    Progress(f64),
+    // a log line along with butler's own level for it
+    Log(log::Level, String),
+}
+
+// turns a line of butler's --json output into something we care about, anything
+// that isn't JSON is passed through as a plain log line
+pub fn parse_event(line: &str) -> Option<Event> {
+    let message: JsonMessage = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(_) => return Some(Event::Log(log::Level::Info, line.to_string())),
+    };
+
+    match message.kind.as_str() {
+        "progress" => message
+            .progress
+            .or_else(|| message.percentage.map(|percentage| percentage / 100.0))
+            .map(|alpha| Event::Progress(alpha.clamp(0.0, 1.0))),
+        "log" => {
+            let level = match message.level.as_deref() {
+                Some("error") => log::Level::Error,
+                Some("warning") => log::Level::Warn,
+                Some("debug") => log::Level::Debug,
+                _ => log::Level::Info,
+            };
+            Some(Event::Log(level, message.message.unwrap_or_default()))
+        }
+        "error" => Some(Event::Log(
+            log::Level::Error,
+            message.message.unwrap_or_default(),
+        )),
+        _ => None,
+    }
+}
+
+// why butler gave up on a patch, going by the errors it logged along the way
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    // the patch or signature couldn't even be read, most likely a bad download
+    Corrupt,
+    // everything was readable but the result doesn't match the signature
+    Mismatch,
+    Unknown,
+}
+
+pub fn classify_failure(errors: &[String]) -> Failure {
+    let errors = errors.join("\n").to_lowercase();
+
+    // a damaged file tends to cascade into mismatches too, so corruption wins
+    if [
+        "corrupt",
+        "unexpected eof",
+        "invalid magic",
+        "decompress",
+        "could not decode",
+    ]
+    .iter()
+    .any(|needle| errors.contains(needle))
+    {
+        Failure::Corrupt
+    } else if ["hash mismatch", "signature mismatch", "does not match"]
+        .iter()
+        .any(|needle| errors.contains(needle))
+    {
+        Failure::Mismatch
+    } else {
+        Failure::Unknown
+    }
+}
@@ -0,0 +1,115 @@
+use std::env;
+use std::path::Path;
+
+use eyre::{eyre, Result};
+use winreg::enums::HKEY_CURRENT_USER;
+use winreg::RegKey;
+
+use crate::manifest;
+
+// websites and other launchers start a game through links like
+//
+//     applauncher://play/unnamed-sdvx-clone
+//     applauncher://play/unnamed-sdvx-clone?target=Vulkan
+//
+// which Windows hands to the launcher as its only argument once the scheme is registered
+pub const SCHEME: &str = "applauncher";
+
+// registered per user, so neither registering nor unregistering needs elevation
+const SCHEME_KEY: &str = r"Software\Classes\applauncher";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepLink {
+    pub app: String,
+    // name of the launch target to go with, instead of whichever is selected by default
+    pub target: Option<String>,
+}
+
+// the link among the command line arguments, if the launcher was started through one
+pub fn from_args() -> Option<String> {
+    let prefix = format!("{}://", SCHEME);
+    env::args().find(|arg| arg.to_lowercase().starts_with(&prefix))
+}
+
+pub fn parse(uri: &str) -> Result<DeepLink> {
+    let invalid = || eyre!("The link {} is not one the AppLauncher understands.", uri);
+
+    let prefix = format!("{}://", SCHEME);
+    if !uri.to_lowercase().starts_with(&prefix) {
+        return Err(invalid());
+    }
+    let rest = &uri[prefix.len()..];
+    let (path, query) = match rest.find('?') {
+        Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+        None => (rest, None),
+    };
+
+    // browsers like to add a trailing slash on their way through
+    let mut segments = path.trim_end_matches('/').split('/');
+    let app = match (segments.next(), segments.next(), segments.next()) {
+        (Some(action), Some(app), None)
+            if action.eq_ignore_ascii_case("play") && !app.is_empty() =>
+        {
+            manifest::normalize_app_id(&decode(app))
+        }
+        _ => return Err(invalid()),
+    };
+
+    let target = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter_map(|pair| {
+            let mut pair = pair.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some("target"), Some(target)) if !target.is_empty() => Some(decode(target)),
+                _ => None,
+            }
+        })
+        .next();
+
+    Ok(DeepLink { app, target })
+}
+
+// just the percent escapes, links only ever carry app ids and target names
+fn decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// points the scheme at this executable, a later --register from a moved launcher replaces it
+pub fn register(exe: &Path) -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (scheme_key, _) = hkcu.create_subkey(SCHEME_KEY)?;
+    scheme_key.set_value("", &"URL:AppLauncher")?;
+    scheme_key.set_value("URL Protocol", &"")?;
+
+    let (command_key, _) = scheme_key.create_subkey(r"shell\open\command")?;
+    command_key.set_value("", &format!("\"{}\" \"%1\"", exe.display()))?;
+    Ok(())
+}
+
+pub fn unregister() -> Result<()> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.delete_subkey_all(SCHEME_KEY) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,48 @@
+use std::env;
+use std::path::Path;
+
+use eyre::{eyre, Result};
+
+use crate::logger;
+use crate::manifest::AppEntry;
+use crate::version;
+
+// how much of the log ends up in a bug report, the interesting part is usually at the end
+const LOG_TAIL_LINES: usize = 50;
+
+// everything a bug report usually ends up asking for, in one pasteable block
+pub fn collect(
+    app: &str,
+    entry: &AppEntry,
+    last_error: &str,
+    data_dir: &Path,
+    redact_install_path: bool,
+) -> String {
+    let log_tail =
+        logger::tail(data_dir, LOG_TAIL_LINES).unwrap_or_else(|_| "<log unavailable>".into());
+
+    let report = format!(
+        "AppLauncher {}\nOS: {} ({})\nApp: {}\nPatch level: {}\nInstall dir: {}\nLast error: {}\n\nLog:\n{}",
+        version(),
+        env::consts::OS,
+        env::consts::ARCH,
+        app,
+        entry.patch,
+        entry.dir.display(),
+        last_error,
+        log_tail
+    );
+
+    // the path usually contains the user's account name
+    let install_dir = entry.dir.display().to_string();
+    if redact_install_path && !install_dir.is_empty() {
+        report.replace(&install_dir, "<install dir>")
+    } else {
+        report
+    }
+}
+
+pub fn copy_to_clipboard(report: &str) -> Result<()> {
+    clipboard_win::set_clipboard_string(report)
+        .map_err(|e| eyre!("The clipboard could not be opened ({}).", e))
+}
@@ -0,0 +1,130 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use eyre::{eyre, Result};
+
+// what placeholders are named after, one a crash left behind is found by it
+pub const RESERVATION_PREFIX: &str = "tmp-file-reserved-";
+
+// a placeholder file holding on to the room downloads are going to need, so another program
+// filling up the disk shows up before an update starts rather than halfway through it. NTFS
+// allocates a file extended with set_len right away, it only shrinks as downloads take over
+pub struct SpaceReservation {
+    path: PathBuf,
+    reserved: Mutex<u64>,
+}
+
+#[cfg(windows)]
+fn is_disk_full(e: &io::Error) -> bool {
+    // ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL
+    matches!(e.raw_os_error(), Some(39) | Some(112))
+}
+
+#[cfg(not(windows))]
+fn is_disk_full(e: &io::Error) -> bool {
+    // ENOSPC
+    e.raw_os_error() == Some(28)
+}
+
+impl SpaceReservation {
+    pub fn reserve(path: PathBuf, bytes: u64) -> Result<SpaceReservation> {
+        let file = File::create(&path)?;
+        if let Err(e) = file.set_len(bytes) {
+            drop(file);
+            let _ = fs::remove_file(&path);
+            log::error!(
+                "could not reserve {} bytes at {}: {}",
+                bytes,
+                path.display(),
+                e
+            );
+            return Err(if is_disk_full(&e) {
+                eyre!(
+                    "There is not enough free disk space for the update, it needs {} MB.",
+                    bytes / (1024 * 1024) + 1
+                )
+            } else {
+                e.into()
+            });
+        }
+
+        log::info!("reserved {} bytes at {}", bytes, path.display());
+        Ok(SpaceReservation {
+            path,
+            reserved: Mutex::new(bytes),
+        })
+    }
+
+    // hands back room right before something is about to fill it
+    pub fn release(&self, bytes: u64) {
+        // not kept open in between, windows wouldn't let it be removed in the end otherwise
+        let mut reserved = self.reserved.lock().unwrap();
+        *reserved = reserved.saturating_sub(bytes);
+        let shrunk = OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .and_then(|file| file.set_len(*reserved));
+        if let Err(e) = shrunk {
+            log::warn!("could not shrink {}: {}", self.path.display(), e);
+        }
+    }
+
+    pub fn reserved(&self) -> u64 {
+        *self.reserved.lock().unwrap()
+    }
+}
+
+impl Drop for SpaceReservation {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// removes placeholders a crash left in `dir`, nothing else ever removes them
+pub fn remove_leftover_reservations(dir: &Path) {
+    for dir_entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !dir_entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(RESERVATION_PREFIX)
+        {
+            continue;
+        }
+        log::info!(
+            "removing the leftover reservation {}",
+            dir_entry.path().display()
+        );
+        if let Err(e) = fs::remove_file(dir_entry.path()) {
+            log::warn!("could not remove {}: {}", dir_entry.path().display(), e);
+        }
+    }
+}
+
+// the drive letter or share a path lives on, compared the way windows does, without case
+#[cfg(windows)]
+fn volume(path: &Path) -> Option<String> {
+    use std::path::Component;
+
+    match fs::canonicalize(path).ok()?.components().next()? {
+        Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().to_lowercase()),
+        _ => None,
+    }
+}
+
+#[cfg(not(windows))]
+fn volume(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(fs::metadata(path).ok()?.dev().to_string())
+}
+
+// whether two directories share their free space, a reservation in one of them already holds
+// room in the other then. not knowing counts as not sharing
+pub fn same_volume(a: &Path, b: &Path) -> bool {
+    match (volume(a), volume(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
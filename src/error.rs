@@ -0,0 +1,105 @@
+use std::io;
+
+use serde::Serialize;
+use thiserror::Error;
+
+// failures that callers need to tell apart, the worker's eyre reports carry these from
+// wherever they happen and kind() recovers them at the boundary
+#[derive(Debug, Error)]
+pub enum Error {
+    // the request itself failed, or the server answered with something other than what was
+    // asked for
+    #[error("{message}")]
+    Network {
+        message: String,
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+    #[error("CRC32 Checksum on {what} did not match.")]
+    Checksum { what: String },
+    #[error(
+        "The patching tool failed to apply patch {patch}, the result does not match its signature."
+    )]
+    Signature { patch: u64 },
+    #[error("Patch {patch} is not signed by the publisher.")]
+    Untrusted { patch: u64 },
+    #[error("The download server sent a web page instead of the update, it is likely misconfigured. Please try again later.")]
+    Delivery { url: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("The patching tool failed to apply patch {patch}.")]
+    Butler { patch: u64 },
+    #[error("The install manifest could not be saved ({reason}).")]
+    Manifest { reason: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Network,
+    Checksum,
+    Signature,
+    Io,
+    Butler,
+    Manifest,
+    Other,
+}
+
+impl ErrorKind {
+    // what the launcher exits with after a failed update, 3 is what every failure used to
+    // exit with and still covers anything unclassified, 1 and 2 are taken by an outdated
+    // launcher and setup that couldn't finish
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::Checksum => 5,
+            ErrorKind::Signature => 6,
+            ErrorKind::Io => 7,
+            ErrorKind::Butler => 8,
+            ErrorKind::Manifest => 9,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network {
+            message: e.to_string(),
+            source: Some(e),
+        }
+    }
+}
+
+impl Error {
+    pub fn network(message: impl Into<String>) -> Error {
+        Error::Network {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Network { .. } | Error::Delivery { .. } => ErrorKind::Network,
+            Error::Checksum { .. } => ErrorKind::Checksum,
+            Error::Signature { .. } | Error::Untrusted { .. } => ErrorKind::Signature,
+            Error::Io(_) => ErrorKind::Io,
+            Error::Butler { .. } => ErrorKind::Butler,
+            Error::Manifest { .. } => ErrorKind::Manifest,
+        }
+    }
+}
+
+// errors that were never wrapped still say enough about themselves to be classified
+pub fn kind(e: &eyre::Report) -> ErrorKind {
+    if let Some(e) = e.downcast_ref::<Error>() {
+        e.kind()
+    } else if e.downcast_ref::<reqwest::Error>().is_some() {
+        ErrorKind::Network
+    } else if e.downcast_ref::<io::Error>().is_some() {
+        ErrorKind::Io
+    } else {
+        ErrorKind::Other
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{DateTime, Duration, Utc};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// the launcher and the game take turns on the install directory through this file in its
+// root, a single line of JSON such as
+//
+//     {"owner":"launcher","pid":4242,"since":"2020-11-21T15:04:05Z"}
+//
+// the launcher holds it while patching and the game is expected to hold it, with "game" as
+// its owner, while running. whoever finds a lock held by a live process of the other side
+// backs off, a lock whose process is gone or that is older than a day is stale and taken over
+pub const LOCK_FILE: &str = ".applauncher.lock";
+
+// nothing legitimately patches or plays for this long, past it the pid was most likely reused
+const STALE_LOCK_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Owner {
+    Launcher,
+    Game,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub owner: Owner,
+    pub pid: u32,
+    pub since: DateTime<Utc>,
+}
+
+impl LockInfo {
+    fn is_stale(&self) -> bool {
+        Utc::now() - self.since > Duration::hours(STALE_LOCK_HOURS) || !process_alive(self.pid)
+    }
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use winapi::shared::minwindef::{DWORD, FALSE};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::minwinbase::STILL_ACTIVE;
+    use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess};
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut exit_code: DWORD = 0;
+        let queried = GetExitCodeProcess(handle, &mut exit_code) != 0;
+        CloseHandle(handle);
+        queried && exit_code == STILL_ACTIVE
+    }
+}
+
+#[cfg(not(windows))]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// whoever holds the install directory right now, stale locks don't count
+pub fn holder(install_dir: &Path) -> Option<LockInfo> {
+    let lock = fs::read_to_string(install_dir.join(LOCK_FILE)).ok()?;
+    serde_json::from_str::<LockInfo>(&lock)
+        .ok()
+        .filter(|lock_info| !lock_info.is_stale())
+}
+
+// removes the lock file once dropped, a crash leaves it behind for the staleness check
+pub struct InstallLock {
+    path: PathBuf,
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn write_lock(path: &Path, owner: Owner) -> io::Result<()> {
+    let lock_info = LockInfo {
+        owner,
+        pid: process::id(),
+        since: Utc::now(),
+    };
+
+    // create_new makes sure two launchers can't both think they got it
+    let mut lock_file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    lock_file.write_all(serde_json::to_string(&lock_info)?.as_bytes())?;
+    lock_file.sync_all()
+}
+
+// takes the install directory for patching, fails while the game or another launcher has it
+pub fn acquire(install_dir: &Path) -> Result<InstallLock> {
+    let path = install_dir.join(LOCK_FILE);
+
+    match write_lock(&path, Owner::Launcher) {
+        Ok(()) => return Ok(InstallLock { path }),
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e.into()),
+        Err(_) => {}
+    }
+
+    if let Some(lock_info) = holder(install_dir) {
+        log::warn!(
+            "install directory is locked by {:?} (pid {}) since {}",
+            lock_info.owner,
+            lock_info.pid,
+            lock_info.since
+        );
+        return Err(match lock_info.owner {
+            Owner::Game => eyre!("The game is running, please close it before updating."),
+            Owner::Launcher => eyre!("Another AppLauncher is already updating the game."),
+        });
+    }
+
+    // whoever held it is gone, a lock that can't even be read counts as theirs too
+    log::warn!("taking over a stale lock on the install directory");
+    fs::remove_file(&path)?;
+    write_lock(&path, Owner::Launcher)?;
+    Ok(InstallLock { path })
+}
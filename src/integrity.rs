@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Local;
+use eyre::{eyre, Result};
+use rayon::prelude::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::disk_space;
+use crate::install_lock::LOCK_FILE;
+
+// the game rewrites its own settings, scores and skins, but never its executables and
+// libraries, so those are the files a size check can be trusted on
+const KEY_EXTENSIONS: [&str; 2] = ["exe", "dll"];
+
+fn is_key_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| {
+            KEY_EXTENSIONS
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(extension))
+        })
+        .is_some()
+}
+
+fn collect(
+    dir: &Path,
+    relative: &Path,
+    wanted: fn(&Path) -> bool,
+    files: &mut BTreeMap<String, u64>,
+) -> io::Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let file_name = dir_entry.file_name();
+        let metadata = dir_entry.metadata()?;
+        let relative = relative.join(&file_name);
+
+        // .itch is butler's bookkeeping, not part of the game
+        if metadata.is_dir() && file_name != ".itch" {
+            collect(&dir_entry.path(), &relative, wanted, files)?;
+        } else if metadata.is_file() && wanted(&relative) {
+            // forward slashes, so the manifest reads the same wherever it was written
+            let key = relative.to_string_lossy().replace('\\', "/");
+            files.insert(key, metadata.len());
+        }
+    }
+
+    Ok(())
+}
+
+// sizes of the key files in an install, keyed by their path relative to it
+pub fn snapshot(install_dir: &Path) -> io::Result<BTreeMap<String, u64>> {
+    let mut key_files = BTreeMap::new();
+    collect(install_dir, Path::new(""), is_key_file, &mut key_files)?;
+    Ok(key_files)
+}
+
+// key files that are gone or no longer the size they were after the last update, only
+// looks at metadata so it stays quick even on large installs
+pub fn check(install_dir: &Path, key_files: &BTreeMap<String, u64>) -> Vec<String> {
+    key_files
+        .iter()
+        .filter(
+            |(key_file, size)| match fs::metadata(install_dir.join(key_file.as_str())) {
+                Ok(metadata) => !metadata.is_file() || metadata.len() != **size,
+                Err(_) => true,
+            },
+        )
+        .map(|(key_file, _)| key_file.clone())
+        .collect()
+}
+
+// what the server lists for every file of a patch, keyed by its path relative to the install
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileChecksum {
+    pub size: u64,
+    pub sha256: String,
+    // where the file can be downloaded on its own, for repairing just the files that need it
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl Report {
+    // extra files are the player's own more often than not, only the other two need a repair
+    pub fn needs_repair(&self) -> bool {
+        !self.missing.is_empty() || !self.modified.is_empty()
+    }
+}
+
+fn sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// whether a file is what the server says it should be, the size gives most mismatches away
+// without reading it
+pub fn matches(path: &Path, checksum: &FileChecksum) -> io::Result<bool> {
+    if fs::metadata(path)?.len() != checksum.size {
+        return Ok(false);
+    }
+    Ok(sha256(path)?.eq_ignore_ascii_case(&checksum.sha256))
+}
+
+// the server names files relative to the install, one that points anywhere outside of it
+// is never written to
+pub fn is_inside_install(file: &str) -> bool {
+    let path = Path::new(file);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+// our lock sits in the install while the launcher runs, and so does the room an update sets
+// aside, neither is ever one of the game's files
+fn is_game_file(relative: &Path) -> bool {
+    relative != Path::new(LOCK_FILE)
+        && !(relative.parent() == Some(Path::new(""))
+            && relative
+                .to_string_lossy()
+                .starts_with(disk_space::RESERVATION_PREFIX))
+}
+
+// reads every file in the install, unlike check, so it can tell apart files that were changed
+// without their size changing. files are hashed in parallel, large installs take a while still
+pub fn verify_files(
+    install_dir: &Path,
+    checksums: &BTreeMap<String, FileChecksum>,
+) -> io::Result<Report> {
+    let mut files = BTreeMap::new();
+    collect(install_dir, Path::new(""), is_game_file, &mut files)?;
+
+    let extra = files
+        .keys()
+        .filter(|file| !checksums.contains_key(file.as_str()))
+        .cloned()
+        .collect();
+    let missing = checksums
+        .keys()
+        .filter(|file| !files.contains_key(file.as_str()))
+        .cloned()
+        .collect();
+    // a size that's off already gives it away, no need to read the file then
+    let mut modified: Vec<String> = checksums
+        .par_iter()
+        .filter(|(file, checksum)| match files.get(file.as_str()) {
+            Some(size) if *size != checksum.size => true,
+            Some(_) => match matches(&install_dir.join(file.as_str()), checksum) {
+                Ok(matched) => !matched,
+                Err(e) => {
+                    log::warn!("could not read {}: {}", file, e);
+                    true
+                }
+            },
+            None => false,
+        })
+        .map(|(file, _)| file.clone())
+        .collect();
+    modified.sort();
+
+    Ok(Report {
+        missing,
+        extra,
+        modified,
+    })
+}
+
+// moves a damaged install out of the way so it can be installed again from scratch, nothing
+// is deleted in case the player kept something of their own in there
+pub fn set_aside(install_dir: &Path) -> Result<PathBuf> {
+    let dir_name = install_dir
+        .file_name()
+        .ok_or_else(|| {
+            eyre!(
+                "The install directory {} has no name.",
+                install_dir.display()
+            )
+        })?
+        .to_string_lossy();
+    let damaged_dir = install_dir.with_file_name(format!(
+        "{}.damaged-{}",
+        dir_name,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    fs::rename(install_dir, &damaged_dir).map_err(|e| {
+        eyre!(
+            "The damaged install could not be moved to {} ({}).",
+            damaged_dir.display(),
+            e
+        )
+    })?;
+    fs::create_dir_all(install_dir)?;
+
+    Ok(damaged_dir)
+}
@@ -0,0 +1,29 @@
+pub mod backups;
+pub mod butler;
+pub mod deep_link;
+pub mod disk_space;
+pub mod error;
+pub mod install_lock;
+pub mod integrity;
+pub mod logger;
+pub mod manifest;
+pub mod paths;
+pub mod self_update;
+pub mod settings;
+pub mod single_instance;
+pub mod uninstall;
+pub mod updater;
+
+pub const CURRENT_VERSION: &str = "0.1.4";
+
+// the commit a build was made from, set by whoever builds it,
+// e.g. APPLAUNCHER_GIT_HASH=$(git rev-parse --short HEAD)
+const GIT_HASH: Option<&str> = option_env!("APPLAUNCHER_GIT_HASH");
+
+// what a bug report needs to tell builds apart, e.g. "v0.1.4 (1a2b3c4)"
+pub fn version() -> String {
+    match GIT_HASH.filter(|git_hash| !git_hash.is_empty()) {
+        Some(git_hash) => format!("v{} ({})", CURRENT_VERSION, git_hash),
+        None => format!("v{}", CURRENT_VERSION),
+    }
+}
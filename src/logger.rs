@@ -0,0 +1,133 @@
+use std::env;
+use std::fs;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::Local;
+use eyre::Result;
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
+
+pub const LOG_FILE: &str = "launcher.log";
+
+lazy_static! {
+    // values that never make it into the log as-is, along with what replaces them
+    static ref REDACTIONS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+}
+
+// the launcher has no console, so everything worth knowing ends up in a file
+struct FileLogger {
+    file: Mutex<fs::File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut message = record.args().to_string();
+        if let Ok(redactions) = REDACTIONS.lock() {
+            for (value, placeholder) in redactions.iter() {
+                message = message.replace(value.as_str(), placeholder);
+            }
+        }
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} [{}] {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                message
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// APPLAUNCHER_LOG wins over the settings file, so support can ask for a debug log in one line
+fn env_level() -> Option<LevelFilter> {
+    env::var("APPLAUNCHER_LOG")
+        .ok()
+        .and_then(|level| level.trim().parse().ok())
+}
+
+pub fn init(data_dir: &Path) -> Result<()> {
+    fs::create_dir_all(data_dir)?;
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(data_dir.join(LOG_FILE))?;
+
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }))?;
+    log::set_max_level(env_level().unwrap_or(LevelFilter::Info));
+
+    Ok(())
+}
+
+// applies the verbosity from the settings file, which is only read after logging has started
+pub fn set_level(level: &str) {
+    if env_level().is_some() {
+        return;
+    }
+
+    match level.trim().parse() {
+        Ok(level) => log::set_max_level(level),
+        Err(_) => log::warn!(
+            "unknown log level {:?}, keeping {}",
+            level,
+            log::max_level()
+        ),
+    }
+}
+
+// replaces every occurrence of a value in anything logged from now on, whatever the level
+pub fn redact(value: &str, placeholder: &str) {
+    if value.is_empty() {
+        return;
+    }
+
+    if let Ok(mut redactions) = REDACTIONS.lock() {
+        redactions.push((value.to_string(), placeholder.to_string()));
+    }
+}
+
+// the last few lines of the log file, for attaching to bug reports
+pub fn tail(data_dir: &Path, lines: usize) -> Result<String> {
+    let log = fs::read_to_string(data_dir.join(LOG_FILE))?;
+    let all_lines: Vec<&str> = log.lines().collect();
+    let start = all_lines.len().saturating_sub(lines);
+
+    Ok(all_lines[start..].join("\n"))
+}
+
+// the end of the log file, at most max_bytes of it, starting on a whole line and marked when
+// earlier lines were left out
+pub fn tail_bytes(data_dir: &Path, max_bytes: u64) -> Result<String> {
+    let mut log_file = fs::File::open(data_dir.join(LOG_FILE))?;
+    let start = log_file.metadata()?.len().saturating_sub(max_bytes);
+    log_file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    log_file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+
+    if start == 0 {
+        return Ok(tail.into_owned());
+    }
+    let first_line = tail.find('\n').map_or(tail.len(), |newline| newline + 1);
+    Ok(format!("[earlier lines left out]\n{}", &tail[first_line..]))
+}
@@ -1,64 +1,84 @@
 #![windows_subsystem = "windows"]
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::env;
 use std::fs;
+use std::future;
 use std::io;
-use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{thread, time};
 
-use crc32c;
-use crossbeam::channel::unbounded;
-use directories_next::ProjectDirs;
+use chrono::Local;
+use crossbeam::channel::{
+    bounded, unbounded, Receiver, RecvTimeoutError, Select, Sender, TryRecvError,
+};
 use eyre::{eyre, Result};
-use iui::controls::{Label, VerticalBox};
+use iui::controls::{
+    Button, Combobox, HorizontalBox, Label, MultilineEntry, Spinbox, TextEntry, VerticalBox,
+};
 use iui::prelude::*;
-use lazy_static::lazy_static;
 use native_dialog::*;
-use octocrab::Octocrab;
-use reqwest::StatusCode;
-use scopeguard::{defer, defer_on_unwind};
+use octocrab::models::repos::{Asset, Release};
+use scopeguard::defer_on_unwind;
 use semver::Version;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
 use tokio::prelude::*;
 
-lazy_static! {
-    static ref HTTP_CLIENT: reqwest::blocking::Client = reqwest::blocking::Client::new();
-    static ref GITHUB_CLIENT: Arc<Octocrab> = octocrab::instance();
-}
+mod diagnostics;
+mod progress_bar;
+mod splash;
+mod tray;
 
-const CURRENT_VERSION: &str = "0.1.4";
+use applauncher::error::{self, ErrorKind};
+use applauncher::updater::{self, CancelToken, WorkerMsg};
+use applauncher::{
+    backups, deep_link, integrity, logger, manifest, paths, self_update, settings, single_instance,
+    uninstall, version, CURRENT_VERSION,
+};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
-struct AppEntry {
-    dir: std::path::PathBuf,
-    patch: u16,
-}
+use manifest::{AppEntry, InstallManifest};
+use progress_bar::ProgressBar;
+use settings::{
+    AfterLaunch, AfterUpdate, Environment, GameSettings, LaunchTarget, ReleaseChannel, Settings,
+    WindowMode,
+};
+use tray::TrayAction;
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
-struct InstallManifest {
-    games: HashMap<String, AppEntry>,
+// octocrab builds its own client, which can't be given the proxy, so GitHub is asked through
+// one of the launcher's and only octocrab's models are used
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_TIMEOUT_SECS: u64 = 15;
+
+fn github_client(settings: &Settings) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .user_agent(format!("OrchestraFM-AppLauncher/{}", CURRENT_VERSION))
+        .timeout(time::Duration::from_secs(GITHUB_TIMEOUT_SECS));
+    let builder = match settings.proxy().as_deref().and_then(updater::parse_proxy) {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    };
+    builder.build().expect("HTTP client failed to initialize.")
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PatchInfo {
-    pub id: u64,
-    pub app: String,
-    pub name: String,
-    pub platform: String,
-    pub issuer: i64,
-    pub url: String,
-    pub hash: u32,
-    pub sig: String,
-    #[serde(rename = "sig_hash")]
-    pub sig_hash: u32,
-    pub arch: String,
+async fn github_get<T: DeserializeOwned>(github: &reqwest::Client, route: &str) -> Result<T> {
+    Ok(github
+        .get(&format!("{}{}", GITHUB_API, route))
+        .header(reqwest::header::ACCEPT, "application/vnd.github.v3+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
 }
 
+// license agreement shipped alongside the launcher, titles without one simply don't bundle it
+const EULA_PATH: &str = "eula.txt";
+
 struct UIState {
     startup: bool,
     startup_text: String,
@@ -71,54 +91,1841 @@ struct UIState {
     error_text: String,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // startup step
+// what the user picked once the update failed, the event loop acts on it
+#[derive(Clone, Copy)]
+enum AfterError {
+    Retry,
+    CopyDiagnostics,
+    Quit,
+}
 
-    // initalize user interface library
-    let user_interface = UI::init().expect("UI library failed to initialize.");
+// libui redraws a label on every set_text, so only touch it when the text actually changed
+struct CachedLabel {
+    label: Label,
+    text: String,
+}
+
+impl CachedLabel {
+    fn new(label: Label) -> Self {
+        CachedLabel {
+            label,
+            text: String::new(),
+        }
+    }
+
+    fn set_text(&mut self, ui: &UI, text: &str) {
+        if self.text != text {
+            self.label.set_text(ui, text);
+            self.text = text.to_string();
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next_unit in ["KB", "MB", "GB"].iter() {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+
+    if unit == "B" {
+        format!("{} {}", bytes, unit)
+    } else {
+        format!("{:.1} {}", size, unit)
+    }
+}
+
+// how much of the session's downloads already went into the month's total
+static RECORDED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// whether the game the launcher started is still running, resident mode can start it again
+// once it exited
+static GAME_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// what this session downloaded, for metered connections. with track_data_usage it's also added
+// to the month's total, only what wasn't yet so it can be called again after a retry
+fn data_usage_summary(data_dir: &Path) -> Option<String> {
+    let downloaded = updater::downloaded_bytes();
+    if downloaded == 0 {
+        return None;
+    }
+    let mut summary = format!("Downloaded {}", format_size(downloaded));
+
+    let unrecorded = downloaded - RECORDED_BYTES.swap(downloaded, Ordering::SeqCst);
+    match Settings::load(data_dir) {
+        Ok(mut settings) if settings.track_data_usage => {
+            let month = Local::now().format("%Y-%m").to_string();
+            let total = settings.add_data_usage(unrecorded, &month);
+            summary += &format!(" ({} this month)", format_size(total));
+            if let Err(e) = settings.save(data_dir) {
+                log::warn!("could not record the data used: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("could not record the data used: {}", e),
+    }
+
+    log::info!("{}", summary);
+    Some(summary)
+}
+
+fn format_duration(duration: time::Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+// how often the download speed is worked out, and how much the newest figure counts against
+// the ones before it so a single slow second doesn't make it jump around
+const RATE_INTERVAL: time::Duration = time::Duration::from_secs(1);
+const RATE_SMOOTHING: f64 = 0.3;
+
+struct RateMeter {
+    since: time::Instant,
+    bytes: u64,
+    bytes_per_sec: Option<f64>,
+}
+
+impl RateMeter {
+    fn new() -> Self {
+        RateMeter {
+            since: time::Instant::now(),
+            bytes: 0,
+            bytes_per_sec: None,
+        }
+    }
+
+    fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+
+    // called on every tick, so a download that stalls shows up as one
+    fn sample(&mut self) {
+        let elapsed = self.since.elapsed();
+        if elapsed < RATE_INTERVAL {
+            return;
+        }
+
+        let rate = self.bytes as f64 / elapsed.as_secs_f64();
+        self.bytes_per_sec = Some(match self.bytes_per_sec {
+            Some(previous) => previous + (rate - previous) * RATE_SMOOTHING,
+            None => rate,
+        });
+        self.since = time::Instant::now();
+        self.bytes = 0;
+    }
+}
+
+#[cfg(windows)]
+fn shift_held() -> bool {
+    use winapi::um::winuser::{GetAsyncKeyState, VK_SHIFT};
+
+    // the high bit is set while the key is down
+    unsafe { (GetAsyncKeyState(VK_SHIFT) as u16 & 0x8000) != 0 }
+}
+
+#[cfg(not(windows))]
+fn shift_held() -> bool {
+    false
+}
+
+// runs a user supplied shell command with the install directory as working directory
+fn run_hook(command: &str, install_dir: &Path) -> Result<()> {
+    let status = if cfg!(target_os = "windows") {
+        process::Command::new("cmd")
+            .args(["/C", command])
+            .current_dir(install_dir)
+            .status()?
+    } else {
+        process::Command::new("sh")
+            .args(["-c", command])
+            .current_dir(install_dir)
+            .status()?
+    };
+
+    if !status.success() {
+        return Err(eyre!("{} exited with {}", command, status));
+    }
+
+    Ok(())
+}
+
+// spawns the game with its configured environment and stamps it as played
+fn launch_game(
+    entry: &AppEntry,
+    game_settings: &GameSettings,
+    target: &LaunchTarget,
+    data_dir: &Path,
+) -> io::Result<process::Child> {
+    log::info!("launching {} ({})", target.name, target.executable);
+    let mut game_cmd = process::Command::new(entry.dir.join(&target.executable));
+    game_settings.apply_env(&mut game_cmd);
+    target.apply(&mut game_cmd);
+    let game = game_cmd.spawn()?;
+
+    if let Err(e) = InstallManifest::record_launch(data_dir, "unnamed-sdvx-clone") {
+        log::warn!("could not record launch time: {}", e);
+    }
+
+    Ok(game)
+}
+
+// waits the game out, offering to start it again whenever it exits with an error so soon after
+// starting that it most likely crashed, a damaged install is pointed out instead
+fn watch_for_crashes(
+    mut game: process::Child,
+    entry: &AppEntry,
+    game_settings: &GameSettings,
+    target: &LaunchTarget,
+    data_dir: &Path,
+    redact_install_path: bool,
+) {
+    let crash_window = time::Duration::from_secs(game_settings.crash_window_secs);
+    loop {
+        let started_at = time::Instant::now();
+        let status = match game.wait() {
+            Ok(status) => status,
+            Err(e) => {
+                log::warn!("lost track of the game: {}", e);
+                return;
+            }
+        };
+        let ran_for = started_at.elapsed();
+        if status.success() || ran_for > crash_window {
+            log::info!(
+                "the game exited with {} after {}s",
+                status,
+                ran_for.as_secs()
+            );
+            return;
+        }
+        log::error!(
+            "the game exited with {} after only {}s, treating it as a crash",
+            status,
+            ran_for.as_secs()
+        );
+
+        // the update may have brought new key files, the manifest on disk has them
+        let entry = InstallManifest::load(data_dir)
+            .ok()
+            .and_then(|manifest| manifest.game("unnamed-sdvx-clone").cloned())
+            .unwrap_or_else(|| entry.clone());
+        let damaged = integrity::check(&entry.dir, &entry.key_files);
+
+        let relaunch = if damaged.is_empty() {
+            MessageConfirm {
+                title: "The game crashed",
+                text: "The game closed with an error right after starting.
+
+Would you like to start it again?",
+                typ: MessageType::Warning,
+            }
+            .show()
+            .unwrap_or(false)
+        } else {
+            log::warn!("missing or damaged game files: {}", damaged.join(", "));
+            let damaged_text = format!(
+                "The game closed with an error right after starting, and {} of its files are missing or damaged.
+
+Start the AppLauncher again to repair the install.",
+                damaged.len()
+            );
+            let _ = MessageAlert {
+                title: "The game crashed",
+                text: &damaged_text,
+                typ: MessageType::Error,
+            }
+            .show();
+            false
+        };
+
+        if !relaunch {
+            let copy_diagnostics = MessageConfirm {
+                title: "The game crashed",
+                text: "Would you like to copy diagnostics to the clipboard for a bug report?",
+                typ: MessageType::Info,
+            }
+            .show()
+            .unwrap_or(false);
+            if copy_diagnostics {
+                let report = diagnostics::collect(
+                    "unnamed-sdvx-clone",
+                    &entry,
+                    &format!(
+                        "The game exited with {} after {}s.",
+                        status,
+                        ran_for.as_secs()
+                    ),
+                    data_dir,
+                    redact_install_path,
+                );
+                if let Err(e) = diagnostics::copy_to_clipboard(&report) {
+                    log::warn!("failed to copy diagnostics: {}", e);
+                }
+            }
+            return;
+        }
+
+        game = match launch_game(&entry, game_settings, target, data_dir) {
+            Ok(game) => game,
+            Err(e) => {
+                log::error!("failed to relaunch the game: {}", e);
+                return;
+            }
+        };
+    }
+}
+
+// how long a shutdown lets the current step finish before killing it
+const SHUTDOWN_GRACE_SECS: u64 = 10;
+
+// what the launcher exits with when it's closed or signalled halfway through an update
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+// lets the helper thread finish the step it's on so the manifest ends up at the last applied
+// patch, then cleans up whatever it left behind. blocks for up to SHUTDOWN_GRACE_SECS, so never
+// call it on the UI thread
+fn shut_down(cancel: &CancelToken, worker_done: &Receiver<()>, code: i32) -> ! {
+    log::info!("shutting down");
+    cancel.stop();
+    let interrupted = matches!(worker_done.try_recv(), Err(TryRecvError::Empty));
+
+    // the helper thread drops its end of the channel once it has returned
+    let grace = time::Duration::from_secs(SHUTDOWN_GRACE_SECS);
+    if let Err(RecvTimeoutError::Timeout) = worker_done.recv_timeout(grace) {
+        log::warn!("helper thread did not stop in time, killing the patching tool");
+        cancel.cancel();
+        let _ = worker_done.recv_timeout(time::Duration::from_secs(1));
+    }
+
+    updater::cleanup_temp_files("unnamed-sdvx-clone");
+    tray::remove();
+    log::logger().flush();
+    process::exit(if interrupted && code == 0 {
+        INTERRUPTED_EXIT_CODE
+    } else {
+        code
+    });
+}
+
+// the window goes away right away while the helper thread winds down in the background
+fn close(
+    user_interface: &UI,
+    window: &mut Window,
+    cancel: &CancelToken,
+    worker_done: Receiver<()>,
+    code: i32,
+) {
+    window.hide(user_interface);
+    let cancel = cancel.clone();
+    thread::spawn(move || shut_down(&cancel, &worker_done, code));
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+// without a console there is nothing to listen on, which is the usual case on Windows
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        future::pending::<()>().await;
+    }
+}
+
+// the value following a flag on the command line, e.g. the file in --export-manifest <file>
+fn arg_value(flag: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != flag).nth(1)
+}
+
+// --export-manifest <file> and --import-manifest <file> carry the installed games over to a
+// new machine, nothing else happens on such a run
+fn transfer_manifest(data_dir: &Path) -> Option<Result<String>> {
+    if let Some(export_path) = arg_value("--export-manifest") {
+        let exported = InstallManifest::load(data_dir)
+            .and_then(|manifest| manifest.export(Path::new(&export_path)))
+            .map(|_| format!("The installed games were exported to {}.", export_path))
+            .map_err(|e| eyre!("The installed games could not be exported ({}).", e));
+        return Some(exported);
+    }
+
+    let import_path = arg_value("--import-manifest")?;
+    let mut manifest = if InstallManifest::exists(data_dir) {
+        match InstallManifest::load(data_dir) {
+            Ok(manifest) => manifest,
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        InstallManifest::default()
+    };
+
+    let imported = manifest
+        .import(Path::new(&import_path))
+        .and_then(|missing| {
+            manifest.save(data_dir)?;
+            Ok(missing)
+        })
+        .map(|missing| {
+            if missing.is_empty() {
+                format!("The installed games were imported from {}.", import_path)
+            } else {
+                format!(
+                    "The installed games were imported from {}, except for these whose install folder could not be found:\n\n{}",
+                    import_path,
+                    missing.join("\n")
+                )
+            }
+        })
+        .map_err(|e| eyre!("The installed games could not be imported ({}).", e));
+    Some(imported)
+}
+
+// --uninstall removes the game's install folder and forgets it, after asking twice since
+// everything the player put in there goes with it. with --json there's nobody to ask, --yes
+// has to be passed instead
+fn uninstall_game(data_dir: &Path, settings: &Settings, json_mode: bool) -> Option<Result<String>> {
+    if !env::args().any(|arg| arg == "--uninstall") {
+        return None;
+    }
+
+    let install_dir = match InstallManifest::load(data_dir)
+        .ok()
+        .and_then(|manifest| manifest.game("unnamed-sdvx-clone").cloned())
+    {
+        Some(entry) => entry.dir,
+        None => return Some(Err(eyre!("The game is not installed."))),
+    };
+
+    let confirmed = if json_mode {
+        env::args().any(|arg| arg == "--yes")
+    } else {
+        let warning_text = format!(
+            "This deletes {} and everything in it, including any songs, scores and settings you added there.\n\nWould you like to uninstall the game?",
+            install_dir.display()
+        );
+        MessageConfirm {
+            title: "Uninstall",
+            text: &warning_text,
+            typ: MessageType::Warning,
+        }
+        .show()
+        .unwrap_or(false)
+            && MessageConfirm {
+                title: "Uninstall",
+                text: "Are you sure? This can't be undone.",
+                typ: MessageType::Warning,
+            }
+            .show()
+            .unwrap_or(false)
+    };
+    if !confirmed {
+        return Some(Err(eyre!("The game was not uninstalled.")));
+    }
+
+    let launch_targets = settings.game("unnamed-sdvx-clone").launch_targets();
+    let uninstalled = uninstall::uninstall(data_dir, "unnamed-sdvx-clone", &launch_targets)
+        .map(|install_dir| format!("The game was uninstalled from {}.", install_dir.display()))
+        .map_err(|e| eyre!("The game could not be uninstalled. {}", e));
+    Some(uninstalled)
+}
+
+// --restore-backup puts the game back the way it was before the last update and locks it to
+// that patch, otherwise the next start would only update it again. like --uninstall it needs
+// --yes with --json
+fn restore_backup(
+    data_dir: &Path,
+    settings: &mut Settings,
+    json_mode: bool,
+) -> Option<Result<String>> {
+    if !env::args().any(|arg| arg == "--restore-backup") {
+        return None;
+    }
+
+    let mut manifest = match InstallManifest::load(data_dir) {
+        Ok(manifest) => manifest,
+        Err(e) => return Some(Err(e)),
+    };
+    let install_dir = match manifest.game("unnamed-sdvx-clone") {
+        Some(entry) => entry.dir.clone(),
+        None => return Some(Err(eyre!("The game is not installed."))),
+    };
+    let backups_dir = backups::backups_dir(data_dir, "unnamed-sdvx-clone");
+    let patch = match backups::list(&backups_dir).last() {
+        Some(patch) => *patch,
+        None => return Some(Err(eyre!("There is no backup of the game to go back to."))),
+    };
+
+    let confirmed = if json_mode {
+        env::args().any(|arg| arg == "--yes")
+    } else {
+        let warning_text = format!(
+            "This puts the game back the way it was at patch {}, anything added to {} since is lost. The game stays at that patch until its version lock is removed.\n\nWould you like to restore the backup?",
+            patch,
+            install_dir.display()
+        );
+        MessageConfirm {
+            title: "Restore Backup",
+            text: &warning_text,
+            typ: MessageType::Warning,
+        }
+        .show()
+        .unwrap_or(false)
+    };
+    if !confirmed {
+        return Some(Err(eyre!("The backup was not restored.")));
+    }
+
+    let restored = backups::restore(&backups_dir, &install_dir)
+        .and_then(|patch| {
+            if let Some(entry) = manifest.game_mut("unnamed-sdvx-clone") {
+                entry.patch = patch;
+                // the next update takes stock of the restored files again
+                entry.key_files.clear();
+            }
+            manifest.save(data_dir)?;
+            settings
+                .games
+                .entry("unnamed-sdvx-clone".into())
+                .or_default()
+                .version_lock = Some(patch);
+            settings.save(data_dir)?;
+            Ok(patch)
+        })
+        .map(|patch| format!("The game was restored to patch {}.", patch))
+        .map_err(|e| eyre!("The backup could not be restored. {}", e));
+    Some(restored)
+}
+
+// failures go out along with their kind, which --json consumers and the exit code go by
+fn send_error(send_state: &Sender<WorkerMsg>, e: &eyre::Report) {
+    send_state
+        .send(WorkerMsg::ErrorKind(error::kind(e)))
+        .unwrap();
+    send_state.send(WorkerMsg::Error(e.to_string())).unwrap();
+}
+
+// the outcome of a one-off action from the command line, the launcher exits right after
+fn finish_action(title: &str, outcome: Result<String>, json_mode: bool) -> ! {
+    let (text, typ, code) = match outcome {
+        Ok(text) => (text, MessageType::Info, 0),
+        Err(e) => (e.to_string(), MessageType::Error, 1),
+    };
+    log::info!("{}", text);
+
+    if json_mode {
+        emit(&if code == 0 {
+            WorkerMsg::Status(text)
+        } else {
+            WorkerMsg::Error(text)
+        });
+    } else {
+        let _ = MessageAlert {
+            title,
+            text: &text,
+            typ,
+        }
+        .show();
+    }
+    process::exit(code);
+}
+
+fn play_offline(send_state: &Sender<WorkerMsg>, reason: &str) {
+    log::info!("launching the installed game without updating");
+    send_state
+        .send(WorkerMsg::Status(format!(
+            "{}, playing without updating.",
+            reason
+        )))
+        .unwrap();
+    send_state.send(WorkerMsg::Done).unwrap();
+}
+
+// with --json there is no window, a parent process renders its own from these lines
+fn emit(worker_msg: &WorkerMsg) {
+    println!("{}", serde_json::to_string(worker_msg).unwrap());
+}
+
+// the --json counterpart to the main event loop, relays the helper thread's messages
+// to stdout and then launches the game without ever showing a dialog
+fn run_headless(
+    recv_state: Receiver<WorkerMsg>,
+    step_timeout: Option<time::Duration>,
+    cancel: CancelToken,
+    entry: AppEntry,
+    game_settings: GameSettings,
+    data_dir: &Path,
+) -> ! {
+    let mut err_occurred = false;
+    let mut error_kind = ErrorKind::Other;
+    loop {
+        let worker_msg = match step_timeout {
+            Some(step_timeout) => recv_state
+                .recv_timeout(step_timeout)
+                .map_err(|e| e.is_timeout()),
+            None => recv_state.recv().map_err(|_| false),
+        };
+
+        match worker_msg {
+            Ok(worker_msg) => {
+                match worker_msg {
+                    WorkerMsg::ErrorKind(kind) => error_kind = kind,
+                    WorkerMsg::Error(_) => err_occurred = true,
+                    // only the window shows the download speed, relaying a line for every read
+                    // would drown out everything else
+                    WorkerMsg::Downloaded(_) => continue,
+                    _ => {}
+                }
+                emit(&worker_msg);
+            }
+            Err(true) => {
+                log::error!("step timed out without progress");
+                cancel.cancel();
+                emit(&WorkerMsg::Error("Step timed out.".into()));
+                err_occurred = true;
+                break;
+            }
+            Err(false) => break,
+        }
+    }
+
+    if let Some(summary) = data_usage_summary(data_dir) {
+        emit(&WorkerMsg::Status(summary));
+    }
+
+    if err_occurred {
+        updater::cleanup_temp_files("unnamed-sdvx-clone");
+        process::exit(error_kind.exit_code());
+    }
+
+    // run the pre-launch hook, only fatal when configured to be
+    if let Some(hook) = game_settings.pre_launch.as_ref() {
+        if let Err(e) = run_hook(hook, &entry.dir) {
+            let hook_text = format!("The pre-launch command failed: {}", e);
+            if game_settings.pre_launch_required {
+                emit(&WorkerMsg::Error(hook_text));
+                process::exit(3);
+            }
+            emit(&WorkerMsg::Status(hook_text));
+        }
+    }
+
+    emit(&WorkerMsg::Status(
+        "Launching requested application.".into(),
+    ));
+    // there is nobody to ask, so headless launches always go with the default target
+    let target = &game_settings.launch_targets()[0];
+    let executables_seen = executables_in_place(&entry.dir, &game_settings.launch_targets());
+    let mut game = match launch_game(&entry, &game_settings, target, data_dir) {
+        Ok(game) => game,
+        Err(e) => {
+            if looks_quarantined(&e, &target.executable, &executables_seen) {
+                emit(&WorkerMsg::Error(format!(
+                    "The game could not be launched: {}, antivirus software may have quarantined or blocked it",
+                    e
+                )));
+            } else {
+                emit(&WorkerMsg::Error(format!(
+                    "The game could not be launched: {}",
+                    e
+                )));
+            }
+            process::exit(3);
+        }
+    };
+
+    if let Some(hook) = game_settings.post_launch.as_ref() {
+        let _ = game.wait();
+        if let Err(e) = run_hook(hook, &entry.dir) {
+            emit(&WorkerMsg::Status(format!(
+                "The post-launch command failed: {}",
+                e
+            )));
+        }
+    }
+
+    process::exit(0);
+}
+
+// an empty, relative or non-directory path can't be installed into, so keep asking
+// until the user picks something usable or cancels
+fn prompt_install_dir() -> Result<Option<PathBuf>> {
+    loop {
+        let install_dir_dialog = OpenSingleDir { dir: None };
+        let install_dir = match install_dir_dialog.show()? {
+            Some(install_dir) => install_dir,
+            None => return Ok(None),
+        };
+
+        let usable = !install_dir.to_string_lossy().trim().is_empty()
+            && install_dir.is_absolute()
+            && install_dir.is_dir();
+
+        // the launcher and butler cope, but the game and whatever else touches its files may not
+        if usable && paths::is_long_install_dir(&install_dir) {
+            log::warn!(
+                "install directory is {} characters long",
+                install_dir.as_os_str().len()
+            );
+            let keep = MessageConfirm {
+                title: "Long install path",
+                text: "The chosen location has a very long path, some of the game's files may end up past the limit Windows puts on paths.\n\nWould you like to use it anyway? Choose No to pick a shorter one.",
+                typ: MessageType::Warning,
+            }
+            .show()?;
+            if !keep {
+                continue;
+            }
+        }
+        if usable {
+            return Ok(Some(install_dir));
+        }
+
+        log::warn!("rejected install directory {:?}", install_dir);
+        MessageAlert {
+            title: "Invalid directory",
+            text: "The chosen location is not a usable directory, please choose another one.",
+            typ: MessageType::Warning,
+        }
+        .show()?;
+    }
+}
+
+// the data directory is there but can't be written to, which happens on locked-down machines,
+// portable mode is offered as the way out since the executable's own directory may still be usable
+fn handle_unwritable_data_dir(data_dir: &Path, e: io::Error, json_mode: bool) -> Result<()> {
+    let data_dir_text = format!(
+        "The AppLauncher can't write to its data folder at {} ({}).",
+        data_dir.display(),
+        e
+    );
+    if json_mode {
+        emit(&WorkerMsg::Error(data_dir_text));
+        process::exit(2);
+    }
+
+    // already portable means the executable's directory was the one that failed
+    let (_, portable_active) = paths::portable();
+    if portable_active {
+        MessageAlert {
+            title: "Data folder not writable",
+            text: &data_dir_text,
+            typ: MessageType::Error,
+        }
+        .show()?;
+        process::exit(2);
+    }
 
-    // make sure there is no updates available for the launcher
-    let latest_release = GITHUB_CLIENT
-        .repos("orchestrafm", "applauncher")
-        .releases()
-        .get_latest()
-        .await?;
-    let latest_version = Version::parse(latest_release.tag_name.strip_prefix("v").unwrap())?;
+    let portable_text = format!(
+        "{}\n\nWould you like to use portable mode instead, keeping the launcher's data in a folder next to it?",
+        data_dir_text
+    );
+    let use_portable = MessageConfirm {
+        title: "Data folder not writable",
+        text: &portable_text,
+        typ: MessageType::Warning,
+    }
+    .show()
+    .unwrap_or(false);
+    if !use_portable {
+        process::exit(2);
+    }
 
-    if latest_version > Version::parse(CURRENT_VERSION)? {
+    // the data directory is resolved once per run, so portable mode needs a fresh start
+    if let Err(e) = paths::enable_portable().and_then(|_| {
+        process::Command::new(env::current_exe()?)
+            .args(env::args().skip(1))
+            .spawn()?;
+        Ok(())
+    }) {
+        let portable_failed_text = format!(
+            "Portable mode could not be turned on: {}\n\nSet APPLAUNCHER_DATA_DIR to a folder you can write to and start the AppLauncher again.",
+            e
+        );
         MessageAlert {
-            title: "Outdated Launcher",
-            text: "Please update to the latest version of the AppLauncher.",
+            title: "Data folder not writable",
+            text: &portable_failed_text,
             typ: MessageType::Error,
         }
         .show()?;
+        process::exit(2);
+    }
+    process::exit(0);
+}
+
+// how many damaged files a dialog lists before summing up the rest
+const MAX_LISTED_FILES: usize = 5;
+
+// sets a damaged install aside so the update that follows installs the game from scratch
+fn repair_install(entry: &mut AppEntry, damaged: &[String], json_mode: bool) -> Result<()> {
+    let damaged_text = format!(
+        "{} game file(s) are missing or damaged, so the game would likely fail to start.",
+        damaged.len()
+    );
+    if json_mode {
+        return Err(eyre!(damaged_text));
+    }
+
+    let mut listed = damaged
+        .iter()
+        .take(MAX_LISTED_FILES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if damaged.len() > MAX_LISTED_FILES {
+        listed.push_str(&format!(
+            "\n...and {} more",
+            damaged.len() - MAX_LISTED_FILES
+        ));
+    }
+    let repair_text = format!(
+        "{}\n\n{}\n\nWould you like to repair it? The current install is moved aside and the game is downloaded again.",
+        damaged_text, listed
+    );
+    let repair = MessageConfirm {
+        title: "Game files damaged",
+        text: &repair_text,
+        typ: MessageType::Warning,
+    }
+    .show()
+    .unwrap_or(false);
+    if !repair {
+        return Err(eyre!(damaged_text));
+    }
+
+    reinstall(entry)
+}
+
+fn reinstall(entry: &mut AppEntry) -> Result<()> {
+    let damaged_dir = integrity::set_aside(&entry.dir)?;
+    log::info!(
+        "moved the damaged install to {}, reinstalling",
+        damaged_dir.display()
+    );
+    entry.patch = 0;
+    entry.key_files.clear();
+    Ok(())
+}
+
+fn list_files(text: &mut String, heading: &str, files: &[String]) {
+    if files.is_empty() {
+        return;
+    }
+    text.push_str(&format!("\n\n{} ({}):", heading, files.len()));
+    for file in files {
+        text.push_str(&format!("\n    {}", file));
+    }
+}
+
+// --verify-files checks the install file by file against the checksums the server lists for
+// its patch, which catches more than butler's verify and doesn't need an update to run. a
+// repair carries on as a reinstall, otherwise the launcher exits once the report is closed
+fn verify_game_files(
+    user_interface: &UI,
+    entry: &mut AppEntry,
+    settings: &Settings,
+    json_mode: bool,
+) -> Result<()> {
+    const TITLE: &str = "Verify Game Files";
+    if entry.patch == 0 {
+        finish_action(
+            TITLE,
+            Err(eyre!("The game has not been installed yet.")),
+            json_mode,
+        );
+    }
+
+    log::info!("verifying the files of patch {}", entry.patch);
+    let checked = updater::fetch_file_manifest(settings, entry.patch, None)
+        .and_then(|checksums| Ok((integrity::verify_files(&entry.dir, &checksums)?, checksums)));
+    let (report, checksums) = match checked {
+        Ok(checked) => checked,
+        Err(e) => finish_action(
+            TITLE,
+            Err(eyre!("The game files could not be verified. {}", e)),
+            json_mode,
+        ),
+    };
+
+    let mut report_text = if report.needs_repair() {
+        format!(
+            "{} missing and {} modified game file(s) were found.",
+            report.missing.len(),
+            report.modified.len()
+        )
+    } else {
+        "All game files are intact.".to_string()
+    };
+    list_files(&mut report_text, "Missing", &report.missing);
+    list_files(&mut report_text, "Modified", &report.modified);
+    list_files(&mut report_text, "Not part of the game", &report.extra);
+    log::info!("{}", report_text);
+
+    if json_mode {
+        let outcome = if report.needs_repair() {
+            Err(eyre!(report_text))
+        } else {
+            Ok(report_text)
+        };
+        finish_action(TITLE, outcome, json_mode);
+    }
+
+    if !show_file_report(user_interface, &report_text, report.needs_repair()) {
+        process::exit(if report.needs_repair() { 1 } else { 0 });
+    }
+
+    // only the files that need it are downloaded again when the server has them on their own
+    let broken: Vec<String> = report
+        .missing
+        .iter()
+        .chain(&report.modified)
+        .cloned()
+        .collect();
+    match updater::repair_files(
+        &entry.dir,
+        &checksums,
+        &broken,
+        settings,
+        &CancelToken::default(),
+    ) {
+        Ok(()) => {
+            log::info!("repaired {} game file(s)", broken.len());
+            let _ = MessageAlert {
+                title: TITLE,
+                text: &format!("{} game file(s) were repaired.", broken.len()),
+                typ: MessageType::Info,
+            }
+            .show();
+            Ok(())
+        }
+        Err(e) => {
+            log::warn!(
+                "could not repair just the broken files, reinstalling: {}",
+                e
+            );
+            reinstall(entry)
+        }
+    }
+}
+
+// the install folder can be deleted behind the launcher's back, the game is either installed
+// again, which the caller takes care of when this returns true, or forgotten
+fn handle_missing_install(
+    manifest: &mut InstallManifest,
+    entry: &AppEntry,
+    data_dir: &Path,
+    json_mode: bool,
+) -> Result<bool> {
+    let missing_text = format!(
+        "The game's folder, {}, no longer exists.",
+        entry.dir.display()
+    );
+    if json_mode {
+        emit(&WorkerMsg::Error(format!(
+            "{} Run the AppLauncher normally to reinstall it.",
+            missing_text
+        )));
+        process::exit(2);
+    }
+
+    let reinstall_text = format!(
+        "{}\n\nWould you like to install the game again? Choose No to remove it from the AppLauncher instead.",
+        missing_text
+    );
+    let reinstall = MessageConfirm {
+        title: "Game folder missing",
+        text: &reinstall_text,
+        typ: MessageType::Warning,
+    }
+    .show()?;
+    if reinstall {
+        log::info!("reinstalling the game");
+        return Ok(true);
+    }
+
+    log::info!("removing the game from the install manifest");
+    manifest
+        .games
+        .remove(&manifest::normalize_app_id("unnamed-sdvx-clone"));
+    manifest.save(data_dir)?;
+    MessageAlert {
+        title: "Game removed",
+        text: "The game was removed from the AppLauncher, start it again whenever you'd like to reinstall it.",
+        typ: MessageType::Info,
+    }
+    .show()?;
+    process::exit(0);
+}
+
+// the update server being down for maintenance is no reason not to play what's installed, a
+// game that isn't installed yet has to wait though
+fn play_during_maintenance(
+    e: &eyre::Report,
+    install_dir: &Path,
+    executable: &str,
+    json_mode: bool,
+) -> bool {
+    let maintenance = match e.downcast_ref::<updater::Maintenance>() {
+        Some(maintenance) if paths::game_executable_exists(install_dir, executable) => maintenance,
+        _ => return false,
+    };
+    if json_mode {
+        return true;
+    }
+
+    let maintenance_text = format!(
+        "{}\n\nWould you like to play the installed game in the meantime?",
+        maintenance
+    );
+    MessageConfirm {
+        title: "Server maintenance",
+        text: &maintenance_text,
+        typ: MessageType::Info,
+    }
+    .show()
+    .unwrap_or(false)
+}
+
+// a directory or an empty leftover named like the game can't be started either
+fn game_executable_runnable(dir: &Path, executable: &str) -> bool {
+    let executable = dir.join(executable);
+    [executable.with_extension("exe"), executable]
+        .iter()
+        .any(|path| match fs::metadata(path) {
+            #[cfg(unix)]
+            Ok(metadata) => {
+                use std::os::unix::fs::PermissionsExt;
+                metadata.is_file()
+                    && metadata.len() > 0
+                    && metadata.permissions().mode() & 0o111 != 0
+            }
+            #[cfg(not(unix))]
+            Ok(metadata) => metadata.is_file() && metadata.len() > 0,
+            Err(_) => false,
+        })
+}
+
+// where the configured registry key says the game was installed, as long as it still is there
+fn registered_install(settings: &Settings) -> Option<PathBuf> {
+    let key = settings.existing_install_key.as_deref()?;
+    let install_dir = paths::registered_install(key, &settings.existing_install_value)?;
+    let launch_targets = settings.game("unnamed-sdvx-clone").launch_targets();
+    if !install_dir.is_dir() || !paths::looks_installed(&install_dir, &launch_targets) {
+        log::info!("the install recorded at {} is gone, not offering it", key);
+        return None;
+    }
+    Some(install_dir)
+}
+
+// the executables of the launch targets that are there right now, whichever one ends up being
+// launched can be told apart from one that never made it onto disk later
+fn executables_in_place(dir: &Path, launch_targets: &[LaunchTarget]) -> Vec<String> {
+    launch_targets
+        .iter()
+        .map(|target| target.executable.clone())
+        .filter(|executable| paths::game_executable_exists(dir, executable))
+        .collect()
+}
+
+// antivirus software likes to quarantine freshly patched executables, which only shows up as
+// the file having vanished or being locked by the time the game is started
+fn looks_quarantined(e: &io::Error, executable: &str, executables_seen: &[String]) -> bool {
+    executables_seen.iter().any(|seen| seen == executable)
+        && matches!(
+            e.kind(),
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+        )
+}
+
+// the download for this platform if the release has one, its release page otherwise
+fn release_download_url(release: &Release) -> String {
+    let is_package = |asset: &&Asset| {
+        let name = asset.name.to_lowercase();
+        [".exe", ".msi", ".zip"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+    };
+
+    release
+        .assets
+        .iter()
+        .filter(is_package)
+        .find(|asset| self_update::is_windows_asset(&asset.name))
+        .or_else(|| release.assets.iter().find(is_package))
+        .map(|asset| asset.browser_download_url.to_string())
+        .unwrap_or_else(|| release.html_url.to_string())
+}
+
+// the newest release worth updating to on the chosen channel, if any, a release GitHub marks
+// as a pre-release counts as one even when its tag doesn't say so
+fn newest_update(
+    releases: Vec<Release>,
+    current: &Version,
+    channel: ReleaseChannel,
+) -> Option<(Version, Release)> {
+    releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .filter(|release| !release.prerelease || channel == ReleaseChannel::Beta)
+        .filter_map(|release| match self_update::parse_tag(&release.tag_name) {
+            Some(version) => Some((version, release)),
+            None => {
+                log::warn!(
+                    "ignoring release with unrecognized tag {}",
+                    release.tag_name
+                );
+                None
+            }
+        })
+        .filter(|(version, _)| self_update::is_update(current, version, channel))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+// the notes of the release a version came from, releases have been tagged both with and
+// without the v
+async fn release_notes(github: &reqwest::Client, version: &str) -> Option<String> {
+    for tag in [format!("v{}", version), version.to_string()].iter() {
+        let route = format!("/repos/orchestrafm/applauncher/releases/tags/{}", tag);
+        match github_get::<Release>(github, &route).await {
+            Ok(release) => {
+                return release.body.filter(|body| !body.trim().is_empty());
+            }
+            Err(e) => log::debug!("no release tagged {}: {}", tag, e),
+        }
+    }
+
+    log::warn!("could not find the release notes for v{}", version);
+    None
+}
+
+// the launcher's releases page, for when there's no particular release to point at
+const RELEASES_URL: &str = "https://github.com/orchestrafm/applauncher/releases/latest";
+
+// tells the user to update the launcher and offers to download it, nothing else happens until
+// they do
+fn require_launcher_update(version: &Version, download_url: &str, json_mode: bool) -> ! {
+    if json_mode {
+        emit(&WorkerMsg::Error(format!(
+            "Please update to the latest version of the AppLauncher: {}",
+            download_url
+        )));
         process::exit(1);
     }
 
+    let outdated_text = format!(
+        "Please update to the latest version of the AppLauncher (v{}).\n\n{}\n\nWould you like to download it now?",
+        version, download_url
+    );
+    let download = MessageConfirm {
+        title: "Outdated Launcher",
+        text: &outdated_text,
+        typ: MessageType::Error,
+    }
+    .show()
+    .unwrap_or(false);
+    if download {
+        open_in_browser(download_url);
+    }
+    process::exit(1);
+}
+
+fn open_in_browser(url: &str) {
+    if let Err(e) = process::Command::new("explorer").arg(url).spawn() {
+        log::warn!("failed to open {}: {}", url, e);
+    }
+}
+
+fn open_folder(dir: &Path) {
+    if let Err(e) = process::Command::new("explorer").arg(dir).spawn() {
+        log::warn!("failed to open {}: {}", dir.display(), e);
+    }
+}
+
+// there is no settings window, the file is plain enough to edit by hand
+fn open_settings(data_dir: &Path) {
+    let settings_path = data_dir.join(settings::SETTINGS_FILE);
+    if !settings_path.exists() {
+        if let Err(e) = Settings::load(data_dir).and_then(|settings| settings.save(data_dir)) {
+            log::warn!("failed to write {}: {}", settings_path.display(), e);
+            return;
+        }
+    }
+    if let Err(e) = process::Command::new("notepad").arg(&settings_path).spawn() {
+        log::warn!("failed to open {}: {}", settings_path.display(), e);
+    }
+}
+
+// brings the other installed games up to date in the background, one after the other, only
+// downloading so nothing changes under a game that might be started from somewhere else
+fn start_prefetch(settings: Settings, data_dir: PathBuf, cancel: CancelToken) {
+    cancel.throttle(settings.background_prefetch_kib_per_sec * 1024);
+    thread::spawn(move || {
+        let manifest = match InstallManifest::load(&data_dir) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                log::warn!(
+                    "not prefetching, the install manifest could not be read: {}",
+                    e
+                );
+                return;
+            }
+        };
+        for (app, entry) in manifest.recent_games() {
+            if app == "unnamed-sdvx-clone" || cancel.is_cancelled() {
+                continue;
+            }
+            if let Err(e) = updater::prefetch(app, entry, &data_dir, &settings, &cancel) {
+                log::warn!("could not prefetch the update for {}: {}", app, e);
+            }
+        }
+    });
+}
+
+fn open_windows_security() {
+    if let Err(e) = process::Command::new("explorer")
+        .arg("windowsdefender://threat/")
+        .spawn()
+    {
+        log::warn!("failed to open Windows Security: {}", e);
+    }
+}
+
+// asks which patch an existing install is on, None means it should be patched from scratch
+fn prompt_patch_level(user_interface: &UI) -> Option<u16> {
+    let answer: Rc<RefCell<Option<Option<u16>>>> = Rc::new(RefCell::new(None));
+
+    let mut prompt_vbox = VerticalBox::new(user_interface);
+    prompt_vbox.set_padded(user_interface, true);
+    let prompt_label = Label::new(
+        user_interface,
+        "Which patch is this install currently on? It will be verified before any updates are applied.",
+    );
+    let patch_spinbox = Spinbox::new(user_interface, 1, u16::MAX.into());
+    let mut button_hbox = HorizontalBox::new(user_interface);
+    button_hbox.set_padded(user_interface, true);
+    let mut adopt_button = Button::new(user_interface, "Use Existing Install");
+    let mut scratch_button = Button::new(user_interface, "Install From Scratch");
+
+    adopt_button.on_clicked(user_interface, {
+        let user_interface = user_interface.clone();
+        let answer = answer.clone();
+        let patch_spinbox = patch_spinbox.clone();
+        move |_| *answer.borrow_mut() = Some(Some(patch_spinbox.value(&user_interface) as u16))
+    });
+    scratch_button.on_clicked(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(None)
+    });
+
+    button_hbox.append(user_interface, adopt_button, LayoutStrategy::Stretchy);
+    button_hbox.append(user_interface, scratch_button, LayoutStrategy::Stretchy);
+    prompt_vbox.append(user_interface, prompt_label, LayoutStrategy::Stretchy);
+    prompt_vbox.append(user_interface, patch_spinbox, LayoutStrategy::Compact);
+    prompt_vbox.append(user_interface, button_hbox, LayoutStrategy::Compact);
+
+    let mut prompt_window = Window::new(
+        user_interface,
+        "Existing Install Found",
+        300,
+        100,
+        WindowType::NoMenubar,
+    );
+    prompt_window.set_margined(user_interface, true);
+    prompt_window.on_closing(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(None)
+    });
+    prompt_window.set_child(user_interface, prompt_vbox);
+    prompt_window.show(user_interface);
+
+    // pump events until the user made a choice, the main event loop isn't running yet
+    let mut event_loop = user_interface.event_loop();
+    while answer.borrow().is_none() {
+        if !event_loop.next_event_tick(user_interface) {
+            break;
+        }
+    }
+    prompt_window.hide(user_interface);
+
+    let answer = *answer.borrow();
+    answer.flatten()
+}
+
+// shows the license agreement until it's accepted or declined, the main event loop isn't running yet
+fn prompt_eula(user_interface: &UI, eula_text: &str) -> bool {
+    let answer: Rc<RefCell<Option<bool>>> = Rc::new(RefCell::new(None));
+
+    let mut prompt_vbox = VerticalBox::new(user_interface);
+    prompt_vbox.set_padded(user_interface, true);
+    let prompt_label = Label::new(
+        user_interface,
+        "Please read and accept the license agreement to continue.",
+    );
+    let mut eula_entry = MultilineEntry::new(user_interface);
+    eula_entry.set_value(user_interface, eula_text);
+    unsafe { ui_sys::uiMultilineEntrySetReadOnly(eula_entry.ptr(), 1) };
+    let mut button_hbox = HorizontalBox::new(user_interface);
+    button_hbox.set_padded(user_interface, true);
+    let mut accept_button = Button::new(user_interface, "Accept");
+    let mut decline_button = Button::new(user_interface, "Decline");
+
+    accept_button.on_clicked(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(true)
+    });
+    decline_button.on_clicked(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(false)
+    });
+
+    button_hbox.append(user_interface, accept_button, LayoutStrategy::Stretchy);
+    button_hbox.append(user_interface, decline_button, LayoutStrategy::Stretchy);
+    prompt_vbox.append(user_interface, prompt_label, LayoutStrategy::Compact);
+    prompt_vbox.append(user_interface, eula_entry, LayoutStrategy::Stretchy);
+    prompt_vbox.append(user_interface, button_hbox, LayoutStrategy::Compact);
+
+    let mut prompt_window = Window::new(
+        user_interface,
+        "License Agreement",
+        480,
+        400,
+        WindowType::NoMenubar,
+    );
+    prompt_window.set_margined(user_interface, true);
+    prompt_window.on_closing(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(false)
+    });
+    prompt_window.set_child(user_interface, prompt_vbox);
+    prompt_window.show(user_interface);
+
+    let mut event_loop = user_interface.event_loop();
+    while answer.borrow().is_none() {
+        if !event_loop.next_event_tick(user_interface) {
+            break;
+        }
+    }
+    prompt_window.hide(user_interface);
+
+    let accepted = answer.borrow().unwrap_or(false);
+    accepted
+}
+
+// true when a repair was asked for, which is only offered when there's something to repair
+fn show_file_report(user_interface: &UI, report_text: &str, offer_repair: bool) -> bool {
+    let answer: Rc<RefCell<Option<bool>>> = Rc::new(RefCell::new(None));
+
+    let mut report_vbox = VerticalBox::new(user_interface);
+    report_vbox.set_padded(user_interface, true);
+    let mut report_entry = MultilineEntry::new(user_interface);
+    report_entry.set_value(user_interface, report_text);
+    unsafe { ui_sys::uiMultilineEntrySetReadOnly(report_entry.ptr(), 1) };
+    let mut button_hbox = HorizontalBox::new(user_interface);
+    button_hbox.set_padded(user_interface, true);
+    if offer_repair {
+        let mut repair_button = Button::new(user_interface, "Repair");
+        repair_button.on_clicked(user_interface, {
+            let answer = answer.clone();
+            move |_| *answer.borrow_mut() = Some(true)
+        });
+        button_hbox.append(user_interface, repair_button, LayoutStrategy::Stretchy);
+    }
+    let mut close_button = Button::new(user_interface, "Close");
+    close_button.on_clicked(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(false)
+    });
+    button_hbox.append(user_interface, close_button, LayoutStrategy::Stretchy);
+    report_vbox.append(user_interface, report_entry, LayoutStrategy::Stretchy);
+    report_vbox.append(user_interface, button_hbox, LayoutStrategy::Compact);
+
+    let mut report_window = Window::new(
+        user_interface,
+        "Verify Game Files",
+        480,
+        400,
+        WindowType::NoMenubar,
+    );
+    report_window.set_margined(user_interface, true);
+    report_window.on_closing(user_interface, {
+        let answer = answer.clone();
+        move |_| *answer.borrow_mut() = Some(false)
+    });
+    report_window.set_child(user_interface, report_vbox);
+    report_window.show(user_interface);
+
+    let mut event_loop = user_interface.event_loop();
+    while answer.borrow().is_none() {
+        if !event_loop.next_event_tick(user_interface) {
+            break;
+        }
+    }
+    report_window.hide(user_interface);
+
+    let repair = answer.borrow().unwrap_or(false);
+    repair
+}
+
+// the log viewer shows this much of the end of the log, the text box slows to a crawl with more
+const LOG_VIEW_BYTES: u64 = 64 * 1024;
+
+// the detailed view keeps the last few lines of the log in the main window
+const LOG_TAIL_BYTES: u64 = 2 * 1024;
+const LOG_TAIL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+fn load_log(user_interface: &UI, log_entry: &mut MultilineEntry, data_dir: &Path) {
+    let log_text = logger::tail_bytes(data_dir, LOG_VIEW_BYTES)
+        .unwrap_or_else(|e| format!("The log could not be read ({}).", e));
+    log_entry.set_value(user_interface, &log_text);
+}
+
+// most people never find the log file, this shows what happened without going looking for it
+fn log_window(user_interface: &UI, data_dir: &Path) -> (Window, MultilineEntry) {
+    let mut log_vbox = VerticalBox::new(user_interface);
+    log_vbox.set_padded(user_interface, true);
+    let log_entry = MultilineEntry::new(user_interface);
+    unsafe { ui_sys::uiMultilineEntrySetReadOnly(log_entry.ptr(), 1) };
+    let mut button_hbox = HorizontalBox::new(user_interface);
+    button_hbox.set_padded(user_interface, true);
+    let mut refresh_button = Button::new(user_interface, "Refresh");
+    let mut copy_button = Button::new(user_interface, "Copy");
+
+    refresh_button.on_clicked(user_interface, {
+        let user_interface = user_interface.clone();
+        let mut log_entry = log_entry.clone();
+        let data_dir = data_dir.to_path_buf();
+        move |_| load_log(&user_interface, &mut log_entry, &data_dir)
+    });
+    copy_button.on_clicked(user_interface, {
+        let user_interface = user_interface.clone();
+        let log_entry = log_entry.clone();
+        move |_| {
+            if let Err(e) = diagnostics::copy_to_clipboard(&log_entry.value(&user_interface)) {
+                log::warn!("could not copy the log: {}", e);
+                let _ = MessageAlert {
+                    title: "Error",
+                    text: &e.to_string(),
+                    typ: MessageType::Error,
+                }
+                .show();
+            }
+        }
+    });
+
+    button_hbox.append(user_interface, refresh_button, LayoutStrategy::Stretchy);
+    button_hbox.append(user_interface, copy_button, LayoutStrategy::Stretchy);
+    log_vbox.append(user_interface, log_entry.clone(), LayoutStrategy::Stretchy);
+    log_vbox.append(user_interface, button_hbox, LayoutStrategy::Compact);
+
+    let mut log_window = Window::new(
+        user_interface,
+        "Launcher Log",
+        640,
+        400,
+        WindowType::NoMenubar,
+    );
+    log_window.set_margined(user_interface, true);
+    log_window.on_closing(user_interface, {
+        let user_interface = user_interface.clone();
+        move |log_window| log_window.hide(&user_interface)
+    });
+    log_window.set_child(user_interface, log_vbox);
+
+    (log_window, log_entry)
+}
+
+// release notes are markdown, shown as they are since there's nothing here to render it
+fn whats_new_window(user_interface: &UI, release_notes: &str) -> Window {
+    let mut notes_vbox = VerticalBox::new(user_interface);
+    notes_vbox.set_padded(user_interface, true);
+    let notes_label = Label::new(
+        user_interface,
+        &format!("The AppLauncher has been updated to v{}.", CURRENT_VERSION),
+    );
+    let mut notes_entry = MultilineEntry::new(user_interface);
+    notes_entry.set_value(user_interface, release_notes);
+    unsafe { ui_sys::uiMultilineEntrySetReadOnly(notes_entry.ptr(), 1) };
+    let mut close_button = Button::new(user_interface, "Close");
+
+    notes_vbox.append(user_interface, notes_label, LayoutStrategy::Compact);
+    notes_vbox.append(user_interface, notes_entry, LayoutStrategy::Stretchy);
+    notes_vbox.append(
+        user_interface,
+        close_button.clone(),
+        LayoutStrategy::Compact,
+    );
+
+    let mut notes_window = Window::new(
+        user_interface,
+        "What's New",
+        480,
+        360,
+        WindowType::NoMenubar,
+    );
+    notes_window.set_margined(user_interface, true);
+    notes_window.on_closing(user_interface, {
+        let user_interface = user_interface.clone();
+        move |notes_window| notes_window.hide(&user_interface)
+    });
+    close_button.on_clicked(user_interface, {
+        let user_interface = user_interface.clone();
+        let mut notes_window = notes_window.clone();
+        move |_| notes_window.hide(&user_interface)
+    });
+    notes_window.set_child(user_interface, notes_vbox);
+
+    notes_window
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // startup step
+
+    // initalize user interface library
+    let user_interface = UI::init().expect("UI library failed to initialize.");
+
+    // holding shift during startup, or passing --safe-mode, skips straight to launching
+    let safe_mode = env::args().any(|arg| arg == "--safe-mode") || shift_held();
+
+    // --json swaps the window and dialogs for status events on stdout, for other launchers to consume
+    let json_mode = env::args().any(|arg| arg == "--json");
+
+    // --local-patches <folder> applies patches from a folder instead of the update server, for
+    // machines without a connection, see updater::local_patch_list
+    let local_patches = arg_value("--local-patches").map(PathBuf::from);
+
     // find user preferences
     let mut manifest = InstallManifest::default();
     let mut entry = AppEntry::default();
-    let mut manifest_found = false;
-    if let Some(proj_dirs) = ProjectDirs::from("fm", "Orchestra FM", "AppLauncher") {
-        let data_local_dir = proj_dirs.data_local_dir();
+    let mut adopted_install = false;
+
+    let data_local_dir = match paths::data_dir() {
+        Ok(data_local_dir) => data_local_dir,
+        Err(e) => {
+            // nothing would persist, not even the log, so don't pretend otherwise
+            if json_mode {
+                emit(&WorkerMsg::Error(e.to_string()));
+                process::exit(2);
+            }
+
+            let data_dir_text = e.to_string();
+            MessageAlert {
+                title: "No data directory",
+                text: &data_dir_text,
+                typ: MessageType::Error,
+            }
+            .show()?;
+            process::exit(2);
+        }
+    };
+    if let Err(e) = paths::ensure_writable(&data_local_dir) {
+        handle_unwritable_data_dir(&data_local_dir, e, json_mode)?;
+    }
+    logger::init(&data_local_dir)?;
+    log::info!("AppLauncher {} starting", version());
+    match paths::portable() {
+        _ if paths::data_dir_from_env() => log::info!(
+            "keeping state in {} as set by APPLAUNCHER_DATA_DIR",
+            data_local_dir.display()
+        ),
+        (true, true) => log::info!("portable mode, keeping state in {}", data_local_dir.display()),
+        (true, false) => log::warn!("portable mode requested but the executable's directory is not writable, using the user profile"),
+        _ => {}
+    }
+    let mut settings = Settings::load(&data_local_dir)?;
+    logger::set_level(&settings.log_level);
+
+    if let Some(transferred) = transfer_manifest(&data_local_dir) {
+        finish_action("Installed Games", transferred, json_mode);
+    }
+    if let Some(uninstalled) = uninstall_game(&data_local_dir, &settings, json_mode) {
+        finish_action("Uninstall", uninstalled, json_mode);
+    }
+    if let Some(restored) = restore_backup(&data_local_dir, &mut settings, json_mode) {
+        finish_action("Restore Backup", restored, json_mode);
+    }
+
+    // --register and --unregister set up applauncher:// links for this user
+    if env::args().any(|arg| arg == "--register") {
+        let registered = env::current_exe()
+            .map_err(eyre::Report::from)
+            .and_then(|exe| deep_link::register(&exe))
+            .map(|_| "AppLauncher links will now open this launcher.".to_string())
+            .map_err(|e| eyre!("AppLauncher links could not be registered ({}).", e));
+        finish_action("AppLauncher Links", registered, json_mode);
+    }
+    if env::args().any(|arg| arg == "--unregister") {
+        let unregistered = deep_link::unregister()
+            .map(|_| "AppLauncher links will no longer open this launcher.".to_string())
+            .map_err(|e| eyre!("AppLauncher links could not be unregistered ({}).", e));
+        finish_action("AppLauncher Links", unregistered, json_mode);
+    }
+
+    // a link goes to the launcher that's already running if there is one, this one only
+    // takes care of it otherwise
+    let deep_link = match deep_link::from_args() {
+        Some(uri) if single_instance::forward(&data_local_dir, &uri) => {
+            log::info!("handed {} over to the running launcher", uri);
+            process::exit(0);
+        }
+        Some(uri) => match deep_link::parse(&uri) {
+            Ok(link) if link.app == "unnamed-sdvx-clone" => {
+                log::info!("started through {}", uri);
+                Some(link)
+            }
+            Ok(link) => finish_action(
+                "AppLauncher Links",
+                Err(eyre!(
+                    "The AppLauncher does not know the game {}.",
+                    link.app
+                )),
+                json_mode,
+            ),
+            Err(e) => finish_action("AppLauncher Links", Err(e), json_mode),
+        },
+        None => None,
+    };
+    let recv_link = single_instance::listen(&data_local_dir)
+        .map_err(|e| log::warn!("links from other launchers won't reach this one: {}", e))
+        .ok();
+
+    // testers switch update servers from the command line, the choice sticks until switched back
+    let requested_environment = if env::args().any(|arg| arg == "--staging") {
+        Some(Environment::Staging)
+    } else if env::args().any(|arg| arg == "--production") {
+        Some(Environment::Production)
+    } else {
+        None
+    };
+    if let Some(environment) = requested_environment {
+        if environment != settings.environment {
+            settings.environment = environment;
+            settings.save(&data_local_dir)?;
+        }
+    }
+    log::info!(
+        "using the {:?} update server at {}",
+        settings.environment,
+        settings.server()
+    );
+    let version_lock = settings.game("unnamed-sdvx-clone").version_lock;
+    if let Some(version_lock) = version_lock {
+        log::info!("version locked to patch {}", version_lock);
+    }
+
+    // a machine that's clearly offline shouldn't sit through GitHub and the update server timing
+    // out one after the other, the installed game is launched right away instead
+    let offline = !safe_mode
+        && !updater::is_online(&settings.proxy().unwrap_or_else(|| settings.server().into()));
+    if offline {
+        log::warn!("no connection, skipping the update checks");
+    }
+
+    // make sure there is no updates available for the launcher, unless the game is pinned to a
+    // patch, then whatever launcher it was pinned with is the one that's wanted. deployments
+    // that keep the launcher up to date themselves skip asking GitHub altogether
+    let self_update_check = settings.self_update_check();
+    let github = github_client(&settings);
+    if !self_update_check {
+        log::info!("not checking for launcher updates, turned off in the settings");
+    }
+    if !safe_mode && !offline && version_lock.is_none() && self_update_check {
+        // the latest release as GitHub sees it skips pre-releases, so look through the recent ones
+        let current_version = Version::parse(CURRENT_VERSION)?;
+        let releases = github_get::<Vec<Release>>(
+            &github,
+            "/repos/orchestrafm/applauncher/releases?per_page=20",
+        )
+        .await;
+
+        match releases {
+            Ok(releases) => {
+                if let Some((latest_version, latest_release)) =
+                    newest_update(releases, &current_version, settings.release_channel)
+                {
+                    let download_url = release_download_url(&latest_release);
+                    log::info!(
+                        "AppLauncher v{} is available at {}",
+                        latest_version,
+                        download_url
+                    );
+                    require_launcher_update(&latest_version, &download_url, json_mode);
+                }
+            }
+            // GitHub being down or rate limiting is no reason to keep the game from starting
+            Err(e) => log::warn!("could not check for launcher updates: {}", e),
+        }
+    }
+
+    // after the launcher has been updated, show once what changed in it. a fresh install has
+    // nothing to compare against, so it only starts keeping track
+    let mut whats_new = None;
+    if !json_mode
+        && !offline
+        && self_update_check
+        && settings.last_seen_version.as_deref() != Some(CURRENT_VERSION)
+    {
+        let fresh_install = !InstallManifest::exists(&data_local_dir);
+        if !fresh_install {
+            whats_new = release_notes(&github, CURRENT_VERSION).await;
+        }
+        if whats_new.is_some() || fresh_install {
+            settings.last_seen_version = Some(CURRENT_VERSION.into());
+            if let Err(e) = settings.save(&data_local_dir) {
+                log::warn!("could not save the last seen launcher version: {}", e);
+            }
+        }
+    }
+
+    // a bundled license agreement has to be accepted before anything is installed or launched,
+    // and again whenever its text changes
+    if let Ok(eula_text) = fs::read_to_string(paths::bundled_path(EULA_PATH)) {
+        let eula_version = format!("{:x}", Sha256::digest(eula_text.as_bytes()));
+        let game_settings = settings
+            .games
+            .entry("unnamed-sdvx-clone".into())
+            .or_default();
+
+        if game_settings.accepted_eula.as_deref() != Some(eula_version.as_str()) {
+            if json_mode {
+                emit(&WorkerMsg::Error(
+                    "The license agreement has not been accepted, run the AppLauncher normally to accept it.".into(),
+                ));
+                process::exit(2);
+            }
+
+            if !prompt_eula(&user_interface, &eula_text) {
+                log::info!("license agreement declined");
+                process::exit(2);
+            }
+            log::info!("license agreement {} accepted", eula_version);
+            game_settings.accepted_eula = Some(eula_version);
+            settings.save(&data_local_dir)?;
+        }
+    }
+
+    let mut reinstalling = false;
+    if InstallManifest::exists(&data_local_dir) {
+        manifest = InstallManifest::load(&data_local_dir)?;
+        for (name, app) in manifest.recent_games() {
+            log::info!(
+                "installed: {} (patch {}, last played {:?})",
+                name,
+                app.patch,
+                app.last_played
+            );
+        }
+
+        // find the app we actually want to update and launch, the map itself is left alone so
+        // every other game is still in it whenever the manifest gets saved
+        if let Some(app) = manifest.game("unnamed-sdvx-clone") {
+            entry = app.clone();
+        }
+
+        // the folder may have been deleted since, don't patch or launch into nothing
+        if manifest.game("unnamed-sdvx-clone").is_some() && !entry.dir.is_dir() {
+            log::warn!("install directory {} no longer exists", entry.dir.display());
+            reinstalling =
+                handle_missing_install(&mut manifest, &entry, &data_local_dir, json_mode)?;
+        }
+    }
+
+    if !InstallManifest::exists(&data_local_dir) || reinstalling {
+        if json_mode {
+            emit(&WorkerMsg::Error(
+                "The game is not installed, run the AppLauncher normally to install it.".into(),
+            ));
+            process::exit(2);
+        }
+
+        if safe_mode {
+            MessageAlert {
+                title: "Safe mode unavailable",
+                text: "Safe mode can only launch a game that is already installed, exiting.",
+                typ: MessageType::Error,
+            }
+            .show()?;
+            process::exit(2);
+        }
+
+        // an install from the standalone installer is offered before asking where to install,
+        // whoever is reinstalling wants a fresh one
+        let mut registered = None;
+        if let Some(install_dir) = registered_install(&settings).filter(|_| !reinstalling) {
+            let adopt_text = format!(
+                "Unnamed SDVX Clone is already installed in {}. Would you like to use it instead of choosing an install location?",
+                install_dir.display()
+            );
+            let adopt = MessageConfirm {
+                title: "Existing install found",
+                text: &adopt_text,
+                typ: MessageType::Info,
+            }
+            .show()?;
+
+            if adopt {
+                registered = prompt_patch_level(&user_interface).map(|patch| (install_dir, patch));
+            }
+        }
+
+        if let Some((install_dir, patch)) = registered {
+            log::info!("adopting the registered install at patch {}", patch);
+            adopted_install = true;
+            entry = AppEntry {
+                dir: install_dir,
+                patch,
+                ..AppEntry::default()
+            };
+            fs::create_dir_all(&data_local_dir)?;
+        } else {
+            // whoever is reinstalling after the folder went missing was asked already
+            if !reinstalling {
+                MessageAlert {
+                    title: "Game not found",
+                    text: "It appears that this game, Unnamed SDVX Clone, is not installed or was not found. You will now be prompted to choose an install location.",
+                    typ: MessageType::Warning,
+                }.show()?;
+            }
 
-        if data_local_dir.join("install.manifest").exists().eq(&false) {
-            MessageAlert {
-                title: "Game not found",
-                text: "It appears that this game, Unnamed SDVX Clone, is not installed or was not found. You will now be prompted to choose an install location.",
-                typ: MessageType::Warning,
-            }.show()?;
+            if let Some(install_dir) = prompt_install_dir()? {
+                let mut patch = 0;
+                let launch_targets = settings.game("unnamed-sdvx-clone").launch_targets();
+                if paths::looks_installed(&install_dir, &launch_targets) {
+                    let adopt = MessageConfirm {
+                        title: "Existing install found",
+                        text: "The chosen directory already appears to contain Unnamed SDVX Clone. Would you like to use it instead of installing from scratch?",
+                        typ: MessageType::Info,
+                    }.show()?;
+
+                    if adopt {
+                        if let Some(level) = prompt_patch_level(&user_interface) {
+                            patch = level;
+                            adopted_install = true;
+                        }
+                    }
+                }
 
-            let install_dir_dialog = OpenSingleDir { dir: None };
-            if let Some(install_dir) = install_dir_dialog.show()? {
                 entry = AppEntry {
                     dir: install_dir,
-                    patch: 0,
+                    patch,
+                    ..AppEntry::default()
                 };
 
                 // create directories while we are at it
-                fs::create_dir_all(data_local_dir).unwrap();
+                fs::create_dir_all(&data_local_dir)?;
             } else {
                 MessageAlert {
                     title: "No directory chosen",
@@ -128,21 +1935,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .show()?;
                 process::exit(2);
             }
-        } else {
-            manifest_found = true;
+        }
+    }
 
-            let deseralized_manifest = fs::read(data_local_dir.join("install.manifest"))?;
-            manifest = toml::from_slice(deseralized_manifest.as_slice())?;
+    if env::args().any(|arg| arg == "--keep-temp") || settings.keep_temp {
+        log::info!(
+            "keeping failed downloads in {}",
+            paths::debug_dir().display()
+        );
+        updater::KEEP_TEMP.store(true, Ordering::SeqCst);
+    }
 
-            // find the app we actually want to update and launch
-            for (name, app) in manifest.games.iter() {
-                if name.eq("unnamed-sdvx-clone") {
-                    entry = app.clone();
-                    manifest.games.remove("unnamed-sdvx-clone".into());
-                    break;
-                }
-            }
-        }
+    if env::args().any(|arg| arg == "--verify-files") {
+        verify_game_files(&user_interface, &mut entry, &settings, json_mode)?;
+    }
+
+    // from here on the install path may only show up in the log as a placeholder
+    if settings.redact_install_path {
+        logger::redact(&entry.dir.display().to_string(), "<install dir>");
     }
 
     // prepare user interface state
@@ -151,47 +1961,209 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         startup_text: "Startup...                                                                                 OK".into(),
         prepare: true,
         prepare_text: "Prepare...                                                                                 OK".into(),
-        update: false,
-        update_text: "Update...".into(),
+        update: safe_mode,
+        update_text: if safe_mode {
+            "Update...                                                                           SKIPPED".into()
+        } else {
+            "Update...".into()
+        },
         launch: false,
         launch_text: "Launch...".into(),
         error_text: "".into(),
     }));
 
+    // a broken splash is left out rather than keeping the game from launching
+    let splash = splash::load().unwrap_or_else(|e| {
+        log::warn!("failed to load the splash image: {}", e);
+        None
+    });
+    let splash_min = if splash.is_some() {
+        time::Duration::from_secs(settings.splash_min_secs)
+    } else {
+        time::Duration::default()
+    };
+    let min_visible = splash_min.max(time::Duration::from_millis(settings.min_visible_ms));
+
+    // only titles that ship more than one executable get to pick which to launch
+    let game_settings = settings.game("unnamed-sdvx-clone");
+    let launch_targets = game_settings.launch_targets();
+    // a link can ask for a target by name, the usual one is picked if there's no such target
+    let linked_target = deep_link
+        .as_ref()
+        .and_then(|link| link.target.as_ref())
+        .and_then(|name| {
+            launch_targets
+                .iter()
+                .position(|target| target.name.eq_ignore_ascii_case(name))
+        });
+    let selected_target = Rc::new(RefCell::new(linked_target.unwrap_or(0)));
+
     // setup and organize controls
-    let (main_vbox, startup_label, prepare_label, update_label, launch_label, error_label) = {
+    let (
+        main_vbox,
+        startup_label,
+        prepare_label,
+        update_label,
+        launch_label,
+        error_label,
+        progress_bar,
+        pause_button,
+        log_button,
+        target_combobox,
+        steps_vbox,
+        stats_vbox,
+        speed_label,
+        log_tail,
+        details_button,
+        error_hbox,
+        retry_button,
+        diagnostics_button,
+        quit_button,
+        play_button,
+    ) = {
         let mut main_vbox = VerticalBox::new(&user_interface);
+        // only there in the detailed view, see the details button
+        let mut steps_vbox = VerticalBox::new(&user_interface);
+        let mut stats_vbox = VerticalBox::new(&user_interface);
         let startup_label = Label::new(&user_interface, "");
         let prepare_label = Label::new(&user_interface, "");
         let update_label = Label::new(&user_interface, "");
         let launch_label = Label::new(&user_interface, "");
         let error_label = Label::new(&user_interface, "");
+        let progress_bar = ProgressBar::new(&user_interface);
+        // only there while downloads are running, see the event loop
+        let mut pause_button = Button::new(&user_interface, "Pause");
+        pause_button.hide(&user_interface);
+        let log_button = Button::new(&user_interface, "View Log");
+        let speed_label = Label::new(&user_interface, "");
+        let log_tail = MultilineEntry::new(&user_interface);
+        unsafe { ui_sys::uiMultilineEntrySetReadOnly(log_tail.ptr(), 1) };
+        let details_button = Button::new(&user_interface, "");
+        // only there once the update has failed
+        let mut error_hbox = HorizontalBox::new(&user_interface);
+        error_hbox.set_padded(&user_interface, true);
+        error_hbox.hide(&user_interface);
+        let retry_button = Button::new(&user_interface, "Retry");
+        let diagnostics_button = Button::new(&user_interface, "Copy Diagnostics");
+        let quit_button = Button::new(&user_interface, "Quit");
+        // only there once the game is ready and waiting to be launched
+        let mut play_button = Button::new(&user_interface, "Play");
+        play_button.hide(&user_interface);
 
-        main_vbox.append(
+        if let Some(splash) = splash {
+            main_vbox.append(
+                &user_interface,
+                splash::area(&user_interface, splash),
+                LayoutStrategy::Stretchy,
+            );
+        }
+        steps_vbox.append(
             &user_interface,
             startup_label.clone(),
             LayoutStrategy::Stretchy,
         );
-        main_vbox.append(
+        steps_vbox.append(
             &user_interface,
             prepare_label.clone(),
             LayoutStrategy::Stretchy,
         );
-        main_vbox.append(
+        steps_vbox.append(
             &user_interface,
             update_label.clone(),
             LayoutStrategy::Stretchy,
         );
-        main_vbox.append(
+        steps_vbox.append(
             &user_interface,
             launch_label.clone(),
             LayoutStrategy::Stretchy,
         );
+        main_vbox.append(
+            &user_interface,
+            steps_vbox.clone(),
+            LayoutStrategy::Stretchy,
+        );
         main_vbox.append(
             &user_interface,
             error_label.clone(),
             LayoutStrategy::Stretchy,
         );
+        error_hbox.append(
+            &user_interface,
+            retry_button.clone(),
+            LayoutStrategy::Stretchy,
+        );
+        error_hbox.append(
+            &user_interface,
+            diagnostics_button.clone(),
+            LayoutStrategy::Stretchy,
+        );
+        error_hbox.append(
+            &user_interface,
+            quit_button.clone(),
+            LayoutStrategy::Stretchy,
+        );
+        main_vbox.append(&user_interface, error_hbox.clone(), LayoutStrategy::Compact);
+        main_vbox.append(
+            &user_interface,
+            play_button.clone(),
+            LayoutStrategy::Compact,
+        );
+        let target_combobox = if launch_targets.len() > 1 {
+            let mut target_combobox = Combobox::new(&user_interface);
+            for target in launch_targets.iter() {
+                target_combobox.append(&user_interface, &target.name);
+            }
+            target_combobox.set_selected(&user_interface, *selected_target.borrow() as i64);
+            target_combobox.on_selected(&user_interface, {
+                let selected_target = selected_target.clone();
+                move |index| {
+                    if index >= 0 {
+                        *selected_target.borrow_mut() = index as usize;
+                    }
+                }
+            });
+            main_vbox.append(
+                &user_interface,
+                target_combobox.clone(),
+                LayoutStrategy::Compact,
+            );
+            Some(target_combobox)
+        } else {
+            None
+        };
+        main_vbox.append(
+            &user_interface,
+            progress_bar.clone(),
+            LayoutStrategy::Compact,
+        );
+        stats_vbox.append(
+            &user_interface,
+            speed_label.clone(),
+            LayoutStrategy::Compact,
+        );
+        stats_vbox.append(&user_interface, log_tail.clone(), LayoutStrategy::Stretchy);
+        main_vbox.append(
+            &user_interface,
+            stats_vbox.clone(),
+            LayoutStrategy::Stretchy,
+        );
+        main_vbox.append(
+            &user_interface,
+            pause_button.clone(),
+            LayoutStrategy::Compact,
+        );
+        main_vbox.append(&user_interface, log_button.clone(), LayoutStrategy::Compact);
+        main_vbox.append(
+            &user_interface,
+            details_button.clone(),
+            LayoutStrategy::Compact,
+        );
+        // for telling support which build this is
+        main_vbox.append(
+            &user_interface,
+            Label::new(&user_interface, &format!("AppLauncher {}", version())),
+            LayoutStrategy::Compact,
+        );
 
         (
             main_vbox,
@@ -200,265 +2172,901 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             update_label,
             launch_label,
             error_label,
+            progress_bar,
+            pause_button,
+            log_button,
+            target_combobox,
+            steps_vbox,
+            stats_vbox,
+            speed_label,
+            log_tail,
+            details_button,
+            error_hbox,
+            retry_button,
+            diagnostics_button,
+            quit_button,
+            play_button,
         )
     };
 
     // connect controls to the main window
+    let mut window_title = String::from("AppLauncher - Orchestra FM");
+    if settings.environment == Environment::Staging {
+        window_title.push_str(" [STAGING]");
+    }
+    if safe_mode {
+        window_title.push_str(" [SAFE MODE]");
+    }
+    if let Some(version_lock) = version_lock {
+        window_title.push_str(&format!(" [LOCKED TO PATCH {}]", version_lock));
+    }
     let mut main_window = Window::new(
         &user_interface,
-        "AppLauncher - Orchestra FM",
+        &window_title,
         300,
         300,
         WindowType::NoMenubar,
     );
     main_window.set_child(&user_interface, main_vbox);
-    main_window.show(&user_interface);
+    if !json_mode {
+        main_window.show(&user_interface);
+        if settings.after_launch == AfterLaunch::Resident {
+            tray::add(&main_window, "AppLauncher", true);
+        }
+    }
+    if let Some(release_notes) = whats_new {
+        whats_new_window(&user_interface, &release_notes).show(&user_interface);
+    }
+    let shown_at = time::Instant::now();
+    let window_mode = Rc::new(Cell::new(settings.window_mode));
 
     // spin up a helper thread
     let mut entry_for_ui = entry.clone();
-    let (send_state, recv_state) = unbounded();
-    let helper_thread = thread::spawn(move || {
-        defer_on_unwind! {
-            send_state.send("An error has occured.".to_string());
-        }
-        // get required updates list
-        send_state.send("Contacting Server...".to_string()).unwrap();
-
-        let mut patch_resp_params: HashMap<String, String> = HashMap::new();
-        patch_resp_params.insert("app".into(), "unnamed-sdvx-clone".into());
-        patch_resp_params.insert("platform".into(), "win32".into());
-        patch_resp_params.insert("version".into(), entry.patch.to_string());
-
-        let patch_list_resp = HTTP_CLIENT
-            .get("https://orchestra.fm/api/v0/patch")
-            .form(&patch_resp_params)
-            .send()
-            .unwrap();
-
-        if patch_list_resp.status().ne(&StatusCode::OK) {
-            send_state
-                .send("ERROR: Update server did not respond.".to_string())
-                .unwrap();
-            return;
+    let step_timeout = settings.step_timeout();
+    let data_dir_for_worker = data_local_dir.clone();
+    let redact_install_path = settings.redact_install_path;
+    let after_launch = settings.after_launch;
+    let cancel = CancelToken::default();
+    let cancel_for_ui = cancel.clone();
+    let prefetch_cancel = CancelToken::default();
+    let mut prefetch_settings = Some(settings.clone()).filter(|settings| {
+        settings.background_prefetch && settings.after_launch == AfterLaunch::Resident
+    });
+    // a failed update can be retried, every run of the helper thread gets its own copies of
+    // what it works with and its own channels, only the latest one is listened to
+    let targets_for_worker = launch_targets.clone();
+    let selected_for_worker = selected_target.clone();
+    let spawn_worker = move |cancel: CancelToken, retry: bool| {
+        let (send_state, recv_state) = unbounded();
+        // whichever target is selected by the time the update starts is the one to play
+        let executable = targets_for_worker[*selected_for_worker.borrow()]
+            .executable
+            .clone();
+        let (send_done, recv_done) = bounded::<()>(0);
+        let settings = settings.clone();
+        let local_patches = local_patches.clone();
+        let mut manifest = manifest.clone();
+        let data_dir_for_worker = data_dir_for_worker.clone();
+        let mut entry = entry.clone();
+        // patches applied before the failure are in the manifest on disk by now
+        if retry {
+            if let Some(saved) = InstallManifest::load(&data_dir_for_worker)
+                .ok()
+                .and_then(|manifest| manifest.game("unnamed-sdvx-clone").cloned())
+                .filter(|saved| saved.dir == entry.dir)
+            {
+                entry = saved;
+            }
         }
-        let patch_list = patch_list_resp.json::<Vec<PatchInfo>>().unwrap();
 
-        // iterate through patch list
-        let total_tasks = patch_list.len() * 5;
-        let mut i = 0;
+        thread::spawn(move || {
+            // dropped however this thread ends, which is what a shutdown waits for
+            let _worker_done = send_done;
+            defer_on_unwind! {
+                send_state.send(WorkerMsg::Error("An error has occured.".to_string()));
+            }
+            // nothing to do, the update step is already marked as skipped
+            if safe_mode {
+                return;
+            }
 
-        let notify_finished_download_task = |total_tasks: usize, i: &mut i32| {
-            *i += 1;
-            send_state
-                .send(format!("Downloading File ({}/{})...", i, total_tasks))
-                .unwrap();
-        };
+            // trying again is asking to go through the server after all
+            if offline && !retry && local_patches.is_none() {
+                if !adopted_install && paths::game_executable_exists(&entry.dir, &executable) {
+                    play_offline(&send_state, "No connection");
+                } else {
+                    send_error(
+                        &send_state,
+                        &eyre!("There is no internet connection, the game can't be installed while offline."),
+                    );
+                }
+                return;
+            }
 
-        let notify_finished_checksum_task = |total_tasks: usize, i: &mut i32| {
-            *i += 1;
-            send_state
-                .send(format!("Comparing File Hashes ({}/{})...", i, total_tasks))
-                .unwrap();
-        };
+            // find out whether the server is there at all before anything else talks to it
+            if settings.health_check && local_patches.is_none() {
+                send_state
+                    .send(WorkerMsg::Status("Checking Server Status...".to_string()))
+                    .unwrap();
 
-        let notify_finished_applying_task = |total_tasks: usize, i: &mut i32| {
-            *i += 1;
-            send_state
-                .send(format!("Applying ({}/{})...", i, total_tasks))
-                .unwrap();
-        };
+                match updater::check_server_health(&settings, Some(&cancel)) {
+                    Ok(()) => {}
+                    Err(_) if cancel.is_cancelled() => return,
+                    Err(e) if play_during_maintenance(&e, &entry.dir, &executable, json_mode) => {
+                        play_offline(&send_state, "Server maintenance");
+                        return;
+                    }
+                    Err(e) => {
+                        send_error(&send_state, &e);
+                        return;
+                    }
+                }
+            }
+
+            // an adopted install has to prove it is on the patch the user claimed first
+            if adopted_install {
+                send_state
+                    .send(WorkerMsg::Status(
+                        "Verifying Existing Install...".to_string(),
+                    ))
+                    .unwrap();
 
-        // TODO: If an error occurs in this loop, persist the manifest anyway
-        for patch in patch_list.iter() {
-            // download patch file
-            notify_finished_download_task(total_tasks, &mut i);
+                if let Err(e) = updater::verify_adopted_install(
+                    &entry,
+                    &settings,
+                    local_patches.as_deref(),
+                    &cancel,
+                ) {
+                    if !cancel.is_cancelled() {
+                        send_error(&send_state, &e);
+                    }
+                    return;
+                }
+            }
 
-            let mut out_patch_file = fs::File::create("tmp-file.pwr").unwrap();
-            defer! { fs::remove_file("tmp-file.pwr").expect(""); }
-            let mut download_patch_resp = HTTP_CLIENT.get(&patch.url).send().expect("");
-            io::copy(&mut download_patch_resp, &mut out_patch_file).expect("");
+            // a quick look at the files that matter, a damaged game gets installed again
+            // rather than launched
+            let mut entry = entry;
+            if settings.verify_before_launch && !entry.key_files.is_empty() {
+                send_state
+                    .send(WorkerMsg::Status("Checking Game Files...".to_string()))
+                    .unwrap();
 
-            // download signature file
-            notify_finished_download_task(total_tasks, &mut i);
+                let damaged = integrity::check(&entry.dir, &entry.key_files);
+                if !damaged.is_empty() {
+                    log::warn!("missing or damaged game files: {}", damaged.join(", "));
+                    if let Err(e) = repair_install(&mut entry, &damaged, json_mode) {
+                        send_error(&send_state, &e);
+                        return;
+                    }
+                }
+            }
 
-            let mut out_sig_file = fs::File::create("tmp-file.pwr.sig").unwrap();
-            defer! { fs::remove_file("tmp-file.pwr.sig").expect(""); }
-            let mut download_sig_resp = HTTP_CLIENT.get(&patch.sig).send().expect("");
-            io::copy(&mut download_sig_resp, &mut out_sig_file).expect("");
+            // get required updates list
+            let patch_list_status = if local_patches.is_some() {
+                "Reading Local Patches..."
+            } else {
+                "Contacting Server..."
+            };
+            send_state
+                .send(WorkerMsg::Status(patch_list_status.to_string()))
+                .unwrap();
 
-            // comparing file checksum
-            notify_finished_checksum_task(total_tasks, &mut i);
+            // not being able to reach the server at all is the most common failure by far, so
+            // offer to try again instead of making the user restart the launcher
+            let mut patch_list = loop {
+                if let Some(local_patches) = local_patches.as_deref() {
+                    match updater::local_patch_list(local_patches, entry.patch) {
+                        Ok(patch_list) => break patch_list,
+                        Err(e) => {
+                            send_error(&send_state, &e);
+                            return;
+                        }
+                    }
+                }
 
-            let patch_file = fs::read("tmp-file.pwr").expect("");
-            let patch_file_crc32c = crc32c::crc32c(patch_file.as_slice());
+                match updater::fetch_patch_list(
+                    &settings,
+                    entry.patch,
+                    Some(&send_state),
+                    Some(&cancel),
+                )
+                .and_then(|patch_list| {
+                    updater::check_hosts(&patch_list, &settings.allowed_hosts())?;
+                    Ok(patch_list)
+                }) {
+                    Ok(patch_list) => break patch_list,
+                    // closing the window while the server is being asked doesn't wait for it
+                    Err(_) if cancel.is_cancelled() => return,
+                    Err(e) if play_during_maintenance(&e, &entry.dir, &executable, json_mode) => {
+                        play_offline(&send_state, "Server maintenance");
+                        return;
+                    }
+                    Err(e) if !json_mode && updater::is_unreachable(&e) => {
+                        log::warn!("update server unreachable: {}", e);
+                        let retry = MessageConfirm {
+                            title: "Update server unreachable",
+                            text: "Could not reach the update server, please check your connection.\n\nWould you like to try again?",
+                            typ: MessageType::Warning,
+                        }
+                        .show()
+                        .unwrap_or(false);
 
-            if patch_file_crc32c.ne(&patch.hash) {
-                println!("Downloaded: {}, Server: {}", patch_file_crc32c, patch.hash);
-                send_state
-                    .send("CRC32 Checksum on patch did not match.".into())
-                    .unwrap();
-                return;
-            }
+                        if !retry {
+                            send_state
+                                .send(WorkerMsg::Error(
+                                    "Could not reach the update server, please check your connection."
+                                        .to_string(),
+                                ))
+                                .unwrap();
+                            return;
+                        }
+                        send_state
+                            .send(WorkerMsg::Status("Contacting Server...".to_string()))
+                            .unwrap();
+                    }
+                    Err(e) => {
+                        // the server can retire launchers before GitHub has caught up, the
+                        // releases page is where the newer one is going to show up
+                        if let Some(too_old) = e.downcast_ref::<updater::ClientTooOld>() {
+                            require_launcher_update(&too_old.required, RELEASES_URL, json_mode);
+                        }
+                        send_error(&send_state, &e);
+                        return;
+                    }
+                }
+            };
 
-            // comparing file checksum
-            notify_finished_checksum_task(total_tasks, &mut i);
+            updater::lock_version(&mut patch_list, entry.patch, version_lock);
 
-            let sig_file = fs::read("tmp-file.pwr.sig").expect("");
-            let sig_file_crc32c = crc32c::crc32c(sig_file.as_slice());
+            manifest
+                .games
+                .insert(String::from("unnamed-sdvx-clone"), entry);
 
-            if sig_file_crc32c.ne(&patch.sig_hash) {
-                println!(
-                    "Downloaded: {}, Server: {}",
-                    sig_file_crc32c, patch.sig_hash
-                );
-                send_state
-                    .send("CRC32 Checksum on signature did not match.".into())
-                    .unwrap();
-                return;
+            // with a download window set, updates are only downloaded inside of it and the game
+            // launches as it is, they're applied once the launcher starts with all of them at hand
+            let downloads_dir = updater::downloads_dir(&data_dir_for_worker);
+            let all_downloaded = patch_list
+                .iter()
+                .all(|patch| updater::is_downloaded(&downloads_dir, patch));
+            let download_window = settings
+                .download_window
+                .as_ref()
+                .filter(|_| local_patches.is_none());
+            if let (Some(window), false) = (download_window, all_downloaded) {
+                match window.contains(Local::now().time()) {
+                    Ok(true) => {
+                        log::info!("inside the download window, downloading the update for later");
+                        match updater::download_ahead(
+                            &patch_list,
+                            &data_dir_for_worker,
+                            &settings,
+                            &send_state,
+                            &cancel,
+                        ) {
+                            Ok(()) if cancel.is_cancelled() => {}
+                            Ok(()) => {
+                                send_state
+                                .send(WorkerMsg::Status(
+                                    "Update downloaded, it will be installed on the next launch."
+                                        .to_string(),
+                                ))
+                                .unwrap();
+                                send_state.send(WorkerMsg::Done).unwrap();
+                            }
+                            Err(e) => send_error(&send_state, &e),
+                        }
+                        return;
+                    }
+                    Ok(false) => {
+                        log::info!(
+                            "outside the download window, the update waits until {}",
+                            window.start
+                        );
+                        send_state
+                            .send(WorkerMsg::Status(format!(
+                                "Update available, it will be downloaded after {}.",
+                                window.start.trim()
+                            )))
+                            .unwrap();
+                        send_state.send(WorkerMsg::Done).unwrap();
+                        return;
+                    }
+                    Err(e) => log::warn!("ignoring the download window: {}", e),
+                }
             }
+            match updater::update(
+                &patch_list,
+                &mut manifest,
+                &data_dir_for_worker,
+                &settings,
+                updater::BUTLER,
+                &send_state,
+                &cancel,
+            ) {
+                Ok(()) if cancel.is_cancelled() => {}
+                Ok(()) => send_state.send(WorkerMsg::Done).unwrap(),
+                Err(e) => send_error(&send_state, &e),
+            }
+        });
 
-            // apply patch to directory
-            notify_finished_applying_task(total_tasks, &mut i);
-
-            fs::create_dir("butler-workingdir").expect("");
-            defer! { fs::remove_dir_all("butler-workingdir").expect("") }
-            let cmd_output = if cfg!(target_os = "windows") {
-                process::Command::new("tools/butler")
-                    .args(&[
-                        "apply",
-                        "--staging-dir",
-                        "butler-workingdir",
-                        "tmp-file.pwr",
-                        entry.dir.to_str().expect(""),
-                        "--signature",
-                        "tmp-file.pwr.sig",
-                    ])
-                    .stdin(process::Stdio::null())
-                    .stdout(process::Stdio::null())
-                    .stderr(process::Stdio::null())
-                    .creation_flags(0x08000000)
-                    .output()
-                    .expect("")
-            } else {
-                process::Command::new("tools/butler")
-                    .args(&[
-                        "apply",
-                        "--staging-dir",
-                        "butler-workingdir",
-                        "tmp-file.pwr",
-                        entry.dir.to_str().expect(""),
-                        "--signature",
-                        "tmp-file.pwr.sig",
-                    ])
-                    .stdin(process::Stdio::null())
-                    .stdout(process::Stdio::null())
-                    .stderr(process::Stdio::null())
-                    .output()
-                    .expect("")
-            };
-            println!(
-                "stdout: {}",
-                std::str::from_utf8(cmd_output.stdout.as_slice()).expect("")
-            );
-            println!(
-                "stderr: {}",
-                std::str::from_utf8(cmd_output.stderr.as_slice()).expect("")
-            );
+        (recv_state, recv_done)
+    };
+    let (recv_state, recv_done) = spawn_worker(cancel.clone(), false);
+    let recv_done = Arc::new(Mutex::new(recv_done));
 
-            entry.patch = patch.id as u16;
+    // what the launcher exits with once it's closed, a failed update stays one until retried
+    let exit_code = Rc::new(Cell::new(0));
+
+    // closing the window, Ctrl+C or SIGTERM all wind the helper thread down instead of
+    // pulling the rug out from under butler
+    main_window.on_closing(&user_interface, {
+        let user_interface = user_interface.clone();
+        let cancel = cancel_for_ui.clone();
+        let recv_done = recv_done.clone();
+        let exit_code = exit_code.clone();
+        move |window| {
+            let worker_done = recv_done.lock().unwrap().clone();
+            close(
+                &user_interface,
+                window,
+                &cancel,
+                worker_done,
+                exit_code.get(),
+            );
         }
-        send_state.send("allok".into()).unwrap();
-        manifest
-            .games
-            .insert(String::from("unnamed-sdvx-clone"), entry);
+    });
+    tokio::spawn({
+        let cancel = cancel_for_ui.clone();
+        let recv_done = recv_done.clone();
+        async move {
+            wait_for_signal().await;
+            let worker_done = recv_done.lock().unwrap().clone();
+            shut_down(&cancel, &worker_done, INTERRUPTED_EXIT_CODE);
+        }
+    });
 
-        // persist manifest to disk
-        if let Some(proj_dirs) = ProjectDirs::from("fm", "Orchestra FM", "AppLauncher") {
-            use std::io::prelude::*;
+    if json_mode {
+        run_headless(
+            recv_state,
+            step_timeout,
+            cancel_for_ui,
+            entry_for_ui,
+            game_settings,
+            &data_local_dir,
+        );
+    }
 
-            let serialized_manifest = toml::to_string(&manifest).unwrap();
+    // pausing only holds up downloads, it lives on the cancel token so nothing about the
+    // window, minimizing included, can lose it
+    let mut pause_button = pause_button;
+    pause_button.on_clicked(&user_interface, {
+        let user_interface = user_interface.clone();
+        let cancel = cancel_for_ui.clone();
+        move |pause_button| {
+            if cancel.is_paused() {
+                log::info!("downloads resumed");
+                cancel.resume();
+                pause_button.set_text(&user_interface, "Pause");
+            } else {
+                log::info!("downloads paused");
+                cancel.pause();
+                pause_button.set_text(&user_interface, "Resume");
+            }
+        }
+    });
 
-            if manifest_found.eq(&false) {
-                let data_local_dir = proj_dirs.data_local_dir();
+    // reloaded every time it's opened, refresh picks up whatever was logged since
+    let (log_window, log_entry) = log_window(&user_interface, &data_local_dir);
+    let mut log_button = log_button;
+    log_button.on_clicked(&user_interface, {
+        let user_interface = user_interface.clone();
+        let mut log_window = log_window.clone();
+        let mut log_entry = log_entry.clone();
+        let data_dir = data_local_dir.clone();
+        move |_| {
+            load_log(&user_interface, &mut log_entry, &data_dir);
+            log_window.show(&user_interface);
+        }
+    });
 
-                let mut manifest_file =
-                    fs::File::create(data_local_dir.join("install.manifest")).unwrap();
-                manifest_file
-                    .write_all(serialized_manifest.as_bytes())
-                    .unwrap();
-                manifest_file.sync_all().unwrap();
-            } else {
-                let data_local_dir = proj_dirs.data_local_dir();
+    // switching views only hides or shows the extra controls, the choice is kept for next time
+    let mut show_window_mode = {
+        let user_interface = user_interface.clone();
+        let mut steps_vbox = steps_vbox.clone();
+        let mut stats_vbox = stats_vbox.clone();
+        let mut details_button = details_button.clone();
+        move |window_mode: WindowMode| match window_mode {
+            WindowMode::Compact => {
+                steps_vbox.hide(&user_interface);
+                stats_vbox.hide(&user_interface);
+                details_button.set_text(&user_interface, "Show Details");
+            }
+            WindowMode::Detailed => {
+                steps_vbox.show(&user_interface);
+                stats_vbox.show(&user_interface);
+                details_button.set_text(&user_interface, "Hide Details");
+            }
+        }
+    };
+    show_window_mode(window_mode.get());
+    let mut details_button = details_button;
+    details_button.on_clicked(&user_interface, {
+        let window_mode = window_mode.clone();
+        let data_dir = data_local_dir.clone();
+        move |_| {
+            let toggled = match window_mode.get() {
+                WindowMode::Compact => WindowMode::Detailed,
+                WindowMode::Detailed => WindowMode::Compact,
+            };
+            window_mode.set(toggled);
+            show_window_mode(toggled);
 
-                fs::write(
-                    data_local_dir.join("install.manifest"),
-                    serialized_manifest.as_bytes(),
-                )
-                .unwrap();
+            // loaded again rather than kept around, anything saved since isn't lost
+            let saved = Settings::load(&data_dir).and_then(|mut settings| {
+                settings.window_mode = toggled;
+                settings.save(&data_dir)
+            });
+            if let Err(e) = saved {
+                log::warn!("could not save the window mode: {}", e);
             }
         }
     });
 
+    // the buttons only leave their choice behind, the event loop owns everything they affect
+    let after_error = Rc::new(Cell::new(None));
+    for (mut button, choice) in [
+        (retry_button, AfterError::Retry),
+        (diagnostics_button, AfterError::CopyDiagnostics),
+        (quit_button, AfterError::Quit),
+    ] {
+        let after_error = after_error.clone();
+        button.on_clicked(&user_interface, move |_| after_error.set(Some(choice)));
+    }
+
+    // a game set to wait after updating is only launched once Play is clicked
+    let play_clicked = Rc::new(Cell::new(false));
+    let mut play_button = play_button;
+    play_button.on_clicked(&user_interface, {
+        let play_clicked = play_clicked.clone();
+        move |_| play_clicked.set(true)
+    });
+
     // main event loop
-    let mut current_operation = String::from("Waiting For Tasks...");
+    let mut current_operation = if safe_mode {
+        String::from("Safe mode: launching without updating.")
+    } else {
+        String::from("Waiting For Tasks...")
+    };
     let mut err_occurred = false;
+    let mut error_kind = ErrorKind::Other;
+    let recv_state = Rc::new(RefCell::new(recv_state));
     let mut event_loop = user_interface.event_loop();
     event_loop.on_tick(&user_interface, {
         // update labels
         let user_interface = user_interface.clone();
-        let mut startup_label = startup_label.clone();
-        let mut prepare_label = prepare_label.clone();
-        let mut update_label = update_label.clone();
-        let mut launch_label = launch_label.clone();
-        let mut error_label = error_label.clone();
+        let mut startup_label = CachedLabel::new(startup_label.clone());
+        let mut prepare_label = CachedLabel::new(prepare_label.clone());
+        let mut update_label = CachedLabel::new(update_label.clone());
+        let mut launch_label = CachedLabel::new(launch_label.clone());
+        let mut error_label = CachedLabel::new(error_label.clone());
+        let mut progress_bar = progress_bar.clone();
+        let mut pause_button = pause_button.clone();
+        let mut pause_shown = false;
+        let mut progress_value = 0;
+        let mut last_activity = time::Instant::now();
+        let mut executables_seen = executables_in_place(&entry_for_ui.dir, &launch_targets);
+        let mut target_combobox = target_combobox.clone();
+        let mut speed_label = CachedLabel::new(speed_label.clone());
+        let mut log_tail = log_tail.clone();
+        let mut log_tail_text = String::new();
+        let mut log_tail_read = time::Instant::now() - LOG_TAIL_INTERVAL;
+        let mut rate_meter = RateMeter::new();
+        let mut update_started: Option<time::Instant> = None;
+        let window_mode = window_mode.clone();
+        let data_dir = data_local_dir.clone();
+        let mut error_hbox = error_hbox.clone();
+        let recv_state = recv_state.clone();
+        let mut play_button = play_button.clone();
+        let mut play_shown = false;
 
         move || {
             let mut ui_state = ui_state.borrow_mut();
 
-            startup_label.set_text(&user_interface, &format!("{}", ui_state.startup_text));
-            prepare_label.set_text(&user_interface, &format!("{}", ui_state.prepare_text));
-            update_label.set_text(&user_interface, &format!("{}", ui_state.update_text));
-            launch_label.set_text(&user_interface, &format!("{}", ui_state.launch_text));
-            error_label.set_text(&user_interface, &format!("{}", current_operation));
+            // links opened while this launcher runs are for the game it's already getting
+            // ready, so all they can still change is the target
+            for uri in recv_link.iter().flat_map(|recv_link| recv_link.try_iter()) {
+                let linked_target = match deep_link::parse(&uri) {
+                    Ok(link) if link.app == "unnamed-sdvx-clone" => link.target.and_then(|name| {
+                        launch_targets
+                            .iter()
+                            .position(|target| target.name.eq_ignore_ascii_case(&name))
+                    }),
+                    Ok(link) => {
+                        log::warn!("ignoring a link to {}, which is not this game", link.app);
+                        None
+                    }
+                    Err(e) => {
+                        log::warn!("ignoring a forwarded link: {}", e);
+                        None
+                    }
+                };
+                log::info!("{} forwarded from another launcher", uri);
+
+                if let (Some(index), false) = (linked_target, ui_state.launch) {
+                    *selected_target.borrow_mut() = index;
+                    if let Some(target_combobox) = target_combobox.as_mut() {
+                        target_combobox.set_selected(&user_interface, index as i64);
+                    }
+                }
+            }
+
+            startup_label.set_text(&user_interface, &ui_state.startup_text);
+            prepare_label.set_text(&user_interface, &ui_state.prepare_text);
+            update_label.set_text(&user_interface, &ui_state.update_text);
+            launch_label.set_text(&user_interface, &ui_state.launch_text);
+
+            // a paused update isn't stuck, the watchdog only counts from when it's resumed
+            let mut stall_text = String::new();
+            if cancel_for_ui.is_paused() && !ui_state.update {
+                last_activity = time::Instant::now();
+                stall_text = " (paused)".into();
+            }
+
+            // watch for steps that stopped reporting anything, warning once they're halfway to the timeout
+            if let (Some(step_timeout), false) = (step_timeout, ui_state.update) {
+                let stalled_for = last_activity.elapsed();
+                if stalled_for >= step_timeout {
+                    log::error!("step timed out after {}s without progress", stalled_for.as_secs());
+                    cancel_for_ui.cancel();
+                    current_operation = "ERROR: Step timed out.".into();
+                    ui_state.update_text = "Update...                                                                                  FAIL".into();
+                    ui_state.update = true;
+                    err_occurred = true;
+                    exit_code.set(error_kind.exit_code());
+                } else if stalled_for >= step_timeout / 2 {
+                    stall_text = format!(
+                        " (no progress for {}s, giving up in {}s)",
+                        stalled_for.as_secs(),
+                        (step_timeout - stalled_for).as_secs()
+                    );
+                }
+            }
+            error_label.set_text(&user_interface, &format!("{}{}", current_operation, stall_text));
+
+            // speed and time left only mean something while the update is running, the time
+            // left goes by the progress bar since that counts applying patches as well
+            rate_meter.sample();
+            let speed_text = match (update_started, ui_state.update) {
+                (Some(_), false) if cancel_for_ui.is_paused() => "Paused".to_string(),
+                (Some(update_started), false) => {
+                    let mut speed_text = rate_meter
+                        .bytes_per_sec
+                        .map(|bytes_per_sec| format!("{}/s", format_size(bytes_per_sec as u64)))
+                        .unwrap_or_default();
+                    if progress_value > 0 {
+                        let elapsed = update_started.elapsed().as_secs_f64();
+                        let left = elapsed * (100 - progress_value) as f64 / progress_value as f64;
+                        if !speed_text.is_empty() {
+                            speed_text.push_str(", ");
+                        }
+                        speed_text.push_str(&format!(
+                            "about {} left",
+                            format_duration(time::Duration::from_secs_f64(left))
+                        ));
+                    }
+                    speed_text
+                }
+                _ => String::new(),
+            };
+            speed_label.set_text(&user_interface, &speed_text);
+
+            if window_mode.get() == WindowMode::Detailed && log_tail_read.elapsed() >= LOG_TAIL_INTERVAL {
+                log_tail_read = time::Instant::now();
+                if let Ok(tail) = logger::tail_bytes(&data_dir, LOG_TAIL_BYTES) {
+                    if tail != log_tail_text {
+                        log_tail.set_value(&user_interface, &tail);
+                        log_tail_text = tail;
+                    }
+                }
+            }
 
             if ui_state.update.eq(&false) {
-                match recv_state.try_recv() {
+                let worker_msg = recv_state.borrow().try_recv();
+                if worker_msg.is_ok() {
+                    last_activity = time::Instant::now();
+                }
+
+                match worker_msg {
                     Err(e) => {
                         if e.is_disconnected().eq(&true) {
                             ui_state.update = true;
                         }
                     }
-                    Ok(performing_operation) => {
-                        if performing_operation.eq("allok") {
-                            current_operation = "Launching requested application.".into();
-                            ui_state.update_text = "Update...                                                                                  OK".into();
-                        } else if performing_operation.contains("error") {
-                            ui_state.update_text = "Update...                                                                                  FAIL".into();
-                            err_occurred = true;
-                        } else {
-                            current_operation = performing_operation;
+                    Ok(WorkerMsg::Status(performing_operation)) => {
+                        current_operation = performing_operation;
+                    }
+                    Ok(WorkerMsg::Progress(alpha)) => {
+                        // several threads report progress, never let the bar move backwards
+                        let value = (alpha * 100.0) as i32;
+                        if value > progress_value {
+                            progress_value = value;
+                            progress_bar.set_value(&user_interface, progress_value);
                         }
                     }
+                    Ok(WorkerMsg::DownloadSize(download_size)) => {
+                        ui_state.update_text = format!("Update... ({})", format_size(download_size));
+                        update_started = Some(time::Instant::now());
+                        pause_button.show(&user_interface);
+                        pause_shown = true;
+                    }
+                    Ok(WorkerMsg::Downloaded(bytes)) => {
+                        rate_meter.add(bytes);
+                    }
+                    Ok(WorkerMsg::ErrorKind(kind)) => {
+                        error_kind = kind;
+                    }
+                    Ok(WorkerMsg::Error(e)) => {
+                        log::error!("update failed: {}", e);
+                        current_operation = format!("ERROR: {}", e);
+                        ui_state.update_text = "Update...                                                                                  FAIL".into();
+                        err_occurred = true;
+                        exit_code.set(error_kind.exit_code());
+                    }
+                    Ok(WorkerMsg::Done) => {
+                        executables_seen = executables_in_place(&entry_for_ui.dir, &launch_targets);
+                        let waiting_for_play = game_settings.after_update == AfterUpdate::Wait && !play_clicked.get();
+                        current_operation = match (data_usage_summary(&data_local_dir), waiting_for_play) {
+                            (Some(summary), false) => format!("{}, launching requested application.", summary),
+                            (Some(summary), true) => format!("{}, press Play to launch the game.", summary),
+                            (None, false) => "Launching requested application.".into(),
+                            (None, true) => "Ready, press Play to launch the game.".into(),
+                        };
+                        ui_state.update_text = "Update...                                                                                  OK".into();
+                        progress_bar.set_value(&user_interface, 100);
+                    }
                 }
             }
 
-            if ui_state.launch.eq(&false) && ui_state.update.eq(&true) {
-                ui_state.launch = true;
+            // background downloads for the other games make way for this one's update
+            if ui_state.update {
+                prefetch_cancel.resume();
+            } else {
+                prefetch_cancel.pause();
+            }
 
-                if err_occurred.eq(&true) {
-                    // notify the user of an error
-                    ui_state.launch_text = "Launch...                                                                               FAIL".into();
+            // nothing left to pause once the update is over, however it ended
+            if pause_shown && ui_state.update {
+                cancel_for_ui.resume();
+                pause_button.hide(&user_interface);
+                pause_shown = false;
+            }
+
+            // the notification area icon's menu, only there in resident mode
+            let mut restart = None;
+            match tray::take_action() {
+                Some(TrayAction::Play) if GAME_RUNNING.load(Ordering::SeqCst) => {
+                    log::info!("the game is already running");
+                }
+                Some(TrayAction::Play) if ui_state.launch => {
+                    ui_state.launch = false;
+                    ui_state.launch_text = "Launch...".into();
+                }
+                // still updating or waiting for Play, the game is launched once it's ready
+                Some(TrayAction::Play) => play_clicked.set(true),
+                Some(TrayAction::CheckForUpdates) if GAME_RUNNING.load(Ordering::SeqCst) => {
                     MessageAlert {
-                        title: "An error has occurred",
-                        text: "Patch checksums did not pass or the patching tool has found an issue with patching the directory. The program will now exit.",
-                        typ: MessageType::Error,
+                        title: "The game is running",
+                        text: "Please close the game before checking for updates.",
+                        typ: MessageType::Info,
                     }.show().expect("");
+                }
+                Some(TrayAction::CheckForUpdates) if ui_state.update => {
+                    log::info!("checking for updates again");
+                    tray::restore(&main_window);
+                    restart = Some("Checking for Updates...");
+                }
+                Some(TrayAction::CheckForUpdates) => log::info!("an update is already running"),
+                Some(TrayAction::OpenFolder) => open_folder(&entry_for_ui.dir),
+                Some(TrayAction::Settings) => open_settings(&data_local_dir),
+                Some(TrayAction::Quit) => {
+                    data_usage_summary(&data_local_dir);
+                    let worker_done = recv_done.lock().unwrap().clone();
+                    close(
+                        &user_interface,
+                        &mut main_window,
+                        &cancel_for_ui,
+                        worker_done,
+                        exit_code.get(),
+                    );
+                }
+                None => {}
+            }
 
-                    process::exit(3);
+            match after_error.take() {
+                Some(AfterError::Retry) => {
+                    log::info!("retrying the update");
+                    restart = Some("Retrying...");
+                }
+                Some(AfterError::CopyDiagnostics) => {
+                    let report = diagnostics::collect(
+                        "unnamed-sdvx-clone",
+                        &entry_for_ui,
+                        &current_operation,
+                        &data_local_dir,
+                        redact_install_path,
+                    );
+                    if let Err(e) = diagnostics::copy_to_clipboard(&report) {
+                        log::warn!("failed to copy diagnostics: {}", e);
+                    }
+                }
+                Some(AfterError::Quit) => {
+                    data_usage_summary(&data_local_dir);
+                    updater::cleanup_temp_files("unnamed-sdvx-clone");
+                    tray::remove();
+                    process::exit(error_kind.exit_code());
+                }
+                None => {}
+            }
+
+            if let Some(restart) = restart {
+                error_hbox.hide(&user_interface);
+                // a step that timed out may still be winding down
+                let _ = recv_done
+                    .lock()
+                    .unwrap()
+                    .recv_timeout(time::Duration::from_secs(SHUTDOWN_GRACE_SECS));
+                cancel_for_ui.reset();
+                let (retry_recv_state, retry_recv_done) = spawn_worker(cancel_for_ui.clone(), true);
+                *recv_state.borrow_mut() = retry_recv_state;
+                *recv_done.lock().unwrap() = retry_recv_done;
+
+                ui_state.update = false;
+                ui_state.update_text = "Update...".into();
+                ui_state.launch = false;
+                ui_state.launch_text = "Launch...".into();
+                play_clicked.set(false);
+                current_operation = restart.into();
+                err_occurred = false;
+                error_kind = ErrorKind::Other;
+                exit_code.set(0);
+                progress_value = 0;
+                progress_bar.set_value(&user_interface, 0);
+                last_activity = time::Instant::now();
+                update_started = None;
+            }
+
+            // fast updates still leave the window and splash up for a moment, failures don't
+            // wait on it
+            let shown_long_enough = err_occurred || shown_at.elapsed() >= min_visible;
+            let waiting_for_play = !err_occurred && game_settings.after_update == AfterUpdate::Wait && !play_clicked.get();
+            if play_shown != (ui_state.update && waiting_for_play) {
+                play_shown = !play_shown;
+                if play_shown {
+                    play_button.show(&user_interface);
+                } else {
+                    play_button.hide(&user_interface);
+                }
+            }
+            if ui_state.launch.eq(&false) && ui_state.update.eq(&true) && shown_long_enough && !waiting_for_play {
+                ui_state.launch = true;
+
+                if err_occurred.eq(&true) {
+                    // notify the user of an error, it's up to them whether to try again
+                    ui_state.launch_text = "Launch...                                                                               FAIL".into();
+                    error_hbox.show(&user_interface);
                 } else {
-                    // launch the application
-                    ui_state.launch_text = "Launch...                                                                                OK".into();
-                    process::Command::new(entry_for_ui.dir.join("usc-game")).spawn().expect("failed to launch application");
+                    // run the pre-launch hook, only fatal when configured to be
+                    if let Some(hook) = game_settings.pre_launch.as_ref() {
+                        if let Err(e) = run_hook(hook, &entry_for_ui.dir) {
+                            let hook_text = format!("The pre-launch command failed: {}", e);
+                            MessageAlert {
+                                title: "Pre-launch command failed",
+                                text: &hook_text,
+                                typ: if game_settings.pre_launch_required { MessageType::Error } else { MessageType::Warning },
+                            }.show().expect("");
+
+                            if game_settings.pre_launch_required {
+                                ui_state.launch_text = "Launch...                                                                               FAIL".into();
+                                process::exit(3);
+                            }
+                        }
+                    }
+
+                    // a game that never made it onto disk is a broken install rather than antivirus
+                    let target = &launch_targets[*selected_target.borrow()];
+                    let executable_seen = executables_seen.contains(&target.executable);
+                    if !executable_seen && !game_executable_runnable(&entry_for_ui.dir, &target.executable) {
+                        log::error!("the game executable is missing or not runnable");
+                        ui_state.launch_text = "Launch...                                                                               FAIL".into();
+                        let missing_text = format!(
+                            "The game executable could not be found in {}, so the install looks incomplete.\n\nTo repair it, delete that folder and start the AppLauncher again to reinstall the game.",
+                            entry_for_ui.dir.display()
+                        );
+                        MessageAlert {
+                            title: "Game not found",
+                            text: &missing_text,
+                            typ: MessageType::Error,
+                        }.show().expect("");
+                        process::exit(3);
+                    }
+
+                    // launch the application, only marked OK once it has actually started
+                    let mut game = match launch_game(&entry_for_ui, &game_settings, target, &data_local_dir) {
+                        Ok(game) => {
+                            ui_state.launch_text = "Launch...                                                                                OK".into();
+                            launch_label.set_text(&user_interface, &ui_state.launch_text);
+                            game
+                        }
+                        Err(e) => {
+                            log::error!("failed to launch the game: {}", e);
+                            ui_state.launch_text = "Launch...                                                                               FAIL".into();
+
+                            if looks_quarantined(&e, &target.executable, &executables_seen) {
+                                log::warn!("the game executable was there after patching, suspecting antivirus");
+                                let quarantine_text = format!(
+                                    "The game could not be started ({}), even though it was in place after updating. This usually means antivirus software quarantined or blocked it.\n\nAdding an exclusion for {} should fix this. Would you like to open Windows Security now?",
+                                    e,
+                                    entry_for_ui.dir.display()
+                                );
+                                let open_security = MessageConfirm {
+                                    title: "Game blocked",
+                                    text: &quarantine_text,
+                                    typ: MessageType::Warning,
+                                }.show().unwrap_or(false);
+                                if open_security {
+                                    open_windows_security();
+                                }
+                            } else {
+                                let launch_text = format!("The game could not be launched: {}", e);
+                                MessageAlert {
+                                    title: "Launch failed",
+                                    text: &launch_text,
+                                    typ: MessageType::Error,
+                                }.show().expect("");
+                            }
+                            process::exit(3);
+                        }
+                    };
+
+                    // stick around until the game exits so the post-launch hook can clean up after
+                    // it, or to pick it back up should it crash, out of the way in the notification area
+                    if after_launch == AfterLaunch::Resident || game_settings.post_launch.is_some() || game_settings.crash_watch {
+                        current_operation = "Waiting for the game to exit...".into();
+                        tray::minimize(&main_window, "AppLauncher is waiting for the game to exit");
+                        let entry = entry_for_ui.clone();
+                        let game_settings = game_settings.clone();
+                        let target = target.clone();
+                        let data_dir = data_local_dir.clone();
+                        GAME_RUNNING.store(true, Ordering::SeqCst);
+                        if let Some(settings) = prefetch_settings.take() {
+                            start_prefetch(settings, data_local_dir.clone(), prefetch_cancel.clone());
+                        }
+                        thread::spawn(move || {
+                            if game_settings.crash_watch {
+                                watch_for_crashes(game, &entry, &game_settings, &target, &data_dir, redact_install_path);
+                            } else {
+                                let _ = game.wait();
+                            }
+                            if let Some(hook) = game_settings.post_launch.as_ref() {
+                                if let Err(e) = run_hook(hook, &entry.dir) {
+                                    let hook_text = format!("The post-launch command failed: {}", e);
+                                    MessageAlert {
+                                        title: "Post-launch command failed",
+                                        text: &hook_text,
+                                        typ: MessageType::Warning,
+                                    }.show().expect("");
+                                }
+                            }
+                            GAME_RUNNING.store(false, Ordering::SeqCst);
+                            // in resident mode it's up to the user when to quit
+                            if after_launch == AfterLaunch::Exit {
+                                tray::remove();
+                                process::exit(0);
+                            }
+                        });
+                        return;
+                    }
                 }
 
                 thread::sleep(time::Duration::from_secs(1)); // Sleep(1) for effect
@@ -468,7 +3076,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    event_loop.run_delay(&user_interface, 16);
+    // iui's run_delay never actually sleeps, so pump libui ourselves and doze until either
+    // the helper thread has something to say or it's time to look at the window again
+    loop {
+        if !event_loop.next_tick(&user_interface) {
+            break;
+        }
+
+        // a retry swaps in the channel of the new helper thread
+        let recv_wakeup = recv_state.borrow().clone();
+        let mut wakeup = Select::new();
+        wakeup.recv(&recv_wakeup);
+        let woke = wakeup
+            .ready_timeout(time::Duration::from_millis(16))
+            .is_ok();
+        if woke && recv_wakeup.is_empty() {
+            // the helper thread is gone, its channel stays ready forever from here on
+            thread::sleep(time::Duration::from_millis(16));
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+pub const MANIFEST_FILE: &str = "install.manifest";
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AppEntry {
+    pub dir: PathBuf,
+    pub patch: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_played: Option<DateTime<Utc>>,
+    // sizes of the executables and libraries as of the last update, checked before launching
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub key_files: BTreeMap<String, u64>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct InstallManifest {
+    pub games: HashMap<String, AppEntry>,
+}
+
+// other tools write manifests too, ids that only differ in case or surrounding whitespace are
+// the same game
+pub fn normalize_app_id(app: &str) -> String {
+    app.trim().to_lowercase()
+}
+
+impl InstallManifest {
+    pub fn exists(data_dir: &Path) -> bool {
+        data_dir.join(MANIFEST_FILE).exists()
+    }
+
+    // keys are normalized on the way in, a manifest that needed it is written back right away
+    // so other tools see the same ids
+    pub fn load(data_dir: &Path) -> Result<InstallManifest> {
+        let deseralized_manifest = fs::read(data_dir.join(MANIFEST_FILE))?;
+        let mut manifest: InstallManifest = toml::from_slice(deseralized_manifest.as_slice())?;
+        if manifest.normalize_keys() {
+            log::info!("normalized the app ids in the install manifest");
+            if let Err(e) = manifest.save(data_dir) {
+                log::warn!("could not save the normalized install manifest: {}", e);
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    // returns whether any key changed, of two entries for the same game the one with the newer
+    // patch is kept
+    pub fn normalize_keys(&mut self) -> bool {
+        if self.games.keys().all(|app| *app == normalize_app_id(app)) {
+            return false;
+        }
+
+        let mut games: Vec<(String, AppEntry)> = self.games.drain().collect();
+        games.sort_by_key(|(_, entry)| entry.patch);
+        for (app, entry) in games {
+            let app = normalize_app_id(&app);
+            if let Some(replaced) = self.games.insert(app.clone(), entry) {
+                log::warn!(
+                    "{} is in the install manifest more than once, dropping the entry at patch {}",
+                    app,
+                    replaced.patch
+                );
+            }
+        }
+
+        true
+    }
+
+    pub fn game(&self, app: &str) -> Option<&AppEntry> {
+        self.games.get(&normalize_app_id(app))
+    }
+
+    pub fn game_mut(&mut self, app: &str) -> Option<&mut AppEntry> {
+        self.games.get_mut(&normalize_app_id(app))
+    }
+
+    // the manifest is written next to the old one and renamed over it, so a crash
+    // halfway through can never leave a truncated manifest behind
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let mut manifest = self.clone();
+        manifest.normalize_keys();
+        let serialized_manifest = toml::to_string(&manifest)?;
+        let staged_path = data_dir.join(format!("{}.tmp", MANIFEST_FILE));
+
+        let mut manifest_file = fs::File::create(&staged_path)?;
+        manifest_file.write_all(serialized_manifest.as_bytes())?;
+        manifest_file.sync_all()?;
+        drop(manifest_file);
+
+        fs::rename(staged_path, data_dir.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    // a copy of the manifest wherever the user wants it, to carry their installs over to a
+    // reinstalled or new machine
+    pub fn export(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    // merges the games of an exported manifest into this one, a game whose install directory
+    // isn't there on this machine is left out and returned instead of being trusted
+    pub fn import(&mut self, path: &Path) -> Result<Vec<String>> {
+        let imported: InstallManifest = toml::from_slice(fs::read(path)?.as_slice())?;
+        let mut missing = Vec::new();
+
+        for (app, entry) in imported.games {
+            let app = normalize_app_id(&app);
+            if entry.dir.is_absolute() && entry.dir.is_dir() {
+                self.games.insert(app, entry);
+            } else {
+                log::warn!(
+                    "not importing {}, {} does not exist",
+                    app,
+                    entry.dir.display()
+                );
+                missing.push(app);
+            }
+        }
+
+        missing.sort();
+        Ok(missing)
+    }
+
+    // most recently played first, games that were never launched go last
+    pub fn recent_games(&self) -> Vec<(&String, &AppEntry)> {
+        let mut games: Vec<(&String, &AppEntry)> = self.games.iter().collect();
+        games.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_played));
+        games
+    }
+
+    // stamps the game as played right now, going through a full load and save
+    pub fn record_launch(data_dir: &Path, app: &str) -> Result<()> {
+        let mut manifest = InstallManifest::load(data_dir)?;
+        if let Some(entry) = manifest.game_mut(app) {
+            entry.last_played = Some(Utc::now());
+            manifest.save(data_dir)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use directories_next::ProjectDirs;
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+use winreg::RegKey;
+
+use crate::settings;
+
+// a file with this name next to the executable keeps all state beside it, for portable drives
+const PORTABLE_SENTINEL: &str = "portable.txt";
+
+lazy_static! {
+    // resolved once, everything the launcher writes has to agree on where it goes
+    static ref BASE_DIRS: Result<BaseDirs, String> = resolve().map_err(|e| e.to_string());
+}
+
+// where the manifest, settings and log go, first match wins:
+//
+// 1. APPLAUNCHER_DATA_DIR, for shared machines and roaming profiles that want it somewhere
+//    specific, relative paths are taken from the working directory
+// 2. a data folder next to the executable in portable mode, if that folder can be written to
+// 3. the local app data folder of the user profile, e.g. %LOCALAPPDATA%\Orchestra FM\AppLauncher
+//
+// whichever it is gets created on startup, a location that can't be written to is reported
+// then rather than failing halfway through an update
+struct BaseDirs {
+    // manifest, settings and log
+    data: PathBuf,
+    data_from_env: bool,
+    // downloaded patches and butler's staging directory
+    temp: PathBuf,
+    portable_requested: bool,
+    portable: bool,
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+}
+
+fn write_test(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(".applauncher-write-test");
+    fs::write(&probe, b"")?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+// read-only media and Program Files both show up as a directory we can't create files in
+fn is_writable(dir: &Path) -> bool {
+    write_test(dir).is_ok()
+}
+
+fn resolve() -> Result<BaseDirs> {
+    let exe_dir = exe_dir();
+    let portable_requested = env::args().any(|arg| arg == "--portable")
+        || exe_dir
+            .as_ref()
+            .map(|dir| dir.join(PORTABLE_SENTINEL))
+            .filter(|sentinel| sentinel.exists())
+            .is_some();
+
+    // the executable's directory when portable, falling back to the profile if it can't be written
+    let portable_dir = exe_dir
+        .filter(|dir| portable_requested && is_writable(dir))
+        .map(|dir| dir.join("data"));
+    let portable = portable_dir.is_some();
+
+    let data_from_env = env::var_os("APPLAUNCHER_DATA_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    let data = if let Some(data_dir) = data_from_env.clone() {
+        let data_dir = if data_dir.is_relative() {
+            env::current_dir()?.join(data_dir)
+        } else {
+            data_dir
+        };
+        if data_dir.is_file() {
+            return Err(eyre!(
+                "APPLAUNCHER_DATA_DIR points at the file {}, it has to be a folder.",
+                data_dir.display()
+            ));
+        }
+        data_dir
+    } else if let Some(portable_dir) = portable_dir.clone() {
+        portable_dir
+    } else {
+        ProjectDirs::from("fm", "Orchestra FM", "AppLauncher")
+            .map(|proj_dirs| proj_dirs.data_local_dir().to_path_buf())
+            .ok_or_else(|| {
+                eyre!("No user profile directory was found to keep the launcher's data in. Set APPLAUNCHER_DATA_DIR to choose one.")
+            })?
+    };
+
+    // temp files have always lived in the working directory, portable installs keep them with the data
+    let temp = match portable_dir {
+        Some(portable_dir) => portable_dir.join("temp"),
+        None => PathBuf::from("."),
+    };
+
+    Ok(BaseDirs {
+        data,
+        data_from_env: data_from_env.is_some(),
+        temp,
+        portable_requested,
+        portable,
+    })
+}
+
+pub fn data_dir() -> Result<PathBuf> {
+    BASE_DIRS
+        .as_ref()
+        .map(|base_dirs| base_dirs.data.clone())
+        .map_err(|e| eyre!(e.clone()))
+}
+
+// settings an administrator provisions for everyone on the machine, e.g.
+// C:\ProgramData\Orchestra FM\AppLauncher\settings.toml, or wherever
+// APPLAUNCHER_ADMIN_SETTINGS points
+pub fn admin_settings_path() -> Option<PathBuf> {
+    if let Some(admin_settings) =
+        env::var_os("APPLAUNCHER_ADMIN_SETTINGS").filter(|path| !path.is_empty())
+    {
+        return Some(PathBuf::from(admin_settings));
+    }
+
+    env::var_os("ProgramData").map(|program_data| {
+        PathBuf::from(program_data)
+            .join("Orchestra FM")
+            .join("AppLauncher")
+            .join(settings::SETTINGS_FILE)
+    })
+}
+
+pub fn temp_dir() -> PathBuf {
+    BASE_DIRS
+        .as_ref()
+        .map(|base_dirs| base_dirs.temp.clone())
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+// where --keep-temp puts the downloads of a failed update
+pub fn debug_dir() -> PathBuf {
+    BASE_DIRS
+        .as_ref()
+        .map(|base_dirs| base_dirs.data.join("debug"))
+        .unwrap_or_else(|_| PathBuf::from("debug"))
+}
+
+pub fn temp_path(name: &str) -> PathBuf {
+    temp_dir().join(name)
+}
+
+// files deployments ship next to the launcher, found there whatever folder it was started in
+pub fn bundled_path(name: &str) -> PathBuf {
+    exe_dir()
+        .map(|exe_dir| exe_dir.join(name))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+// the classic limit on a whole path in Windows, the game's own files sit a few directories
+// below the install directory so that needs to leave them some room
+pub const MAX_PATH: usize = 260;
+const INSTALL_PATH_HEADROOM: usize = 100;
+
+pub fn is_long_install_dir(dir: &Path) -> bool {
+    dir.as_os_str().len() > MAX_PATH - INSTALL_PATH_HEADROOM
+}
+
+// the \\?\ form of an absolute path isn't held to MAX_PATH, std already does this for the
+// launcher's own IO but butler gets its paths as arguments. it also turns off any cleaning up
+// of the path, so slashes have to be made backslashes first
+pub fn extended_length(path: &Path) -> PathBuf {
+    let path_text = match path.to_str() {
+        Some(path_text) if path.is_absolute() && !path_text.starts_with(r"\\?\") => {
+            path_text.replace('/', "\\")
+        }
+        _ => return path.to_path_buf(),
+    };
+
+    match path_text.strip_prefix(r"\\") {
+        Some(unc_path) => PathBuf::from(format!(r"\\?\UNC\{}", unc_path)),
+        None => PathBuf::from(format!(r"\\?\{}", path_text)),
+    }
+}
+
+// only long paths get the prefix, some tools still trip over it
+pub fn long_path_safe(path: &Path) -> PathBuf {
+    if is_long_install_dir(path) {
+        extended_length(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// the directory another installer recorded in the registry, `key` is written out in full like
+// HKEY_LOCAL_MACHINE\SOFTWARE\Unnamed SDVX Clone with the short HKLM and HKCU also understood
+pub fn registered_install(key: &str, value: &str) -> Option<PathBuf> {
+    let (root, subkey) = key.split_at(key.find('\\')?);
+    let root = match root.to_uppercase().as_str() {
+        "HKEY_CURRENT_USER" | "HKCU" => RegKey::predef(HKEY_CURRENT_USER),
+        "HKEY_LOCAL_MACHINE" | "HKLM" => RegKey::predef(HKEY_LOCAL_MACHINE),
+        _ => {
+            log::warn!("{} is not under a registry root the launcher reads", key);
+            return None;
+        }
+    };
+
+    let install_dir: String = match root
+        .open_subkey(subkey.trim_start_matches('\\'))
+        .and_then(|install_key| install_key.get_value(value))
+    {
+        Ok(install_dir) => install_dir,
+        Err(e) => {
+            log::info!("no install recorded at {}\\{}: {}", key, value, e);
+            return None;
+        }
+    };
+    // some installers write the path quoted
+    let install_dir = install_dir.trim().trim_matches('"');
+    Some(PathBuf::from(install_dir)).filter(|_| !install_dir.is_empty())
+}
+
+pub fn game_executable_exists(dir: &Path, executable: &str) -> bool {
+    let executable = dir.join(executable);
+    executable.exists() || executable.with_extension("exe").exists()
+}
+
+// either one of the game's executables or a receipt left behind by butler/itch means something
+// is already here
+pub fn looks_installed(dir: &Path, launch_targets: &[settings::LaunchTarget]) -> bool {
+    launch_targets
+        .iter()
+        .any(|target| game_executable_exists(dir, &target.executable))
+        || dir.join(".itch").join("receipt.json.gz").exists()
+}
+
+// drive roots and folders that hold far more than a game, or contain one that does. a game
+// installed straight into one of them can't have its folder removed as a whole
+pub fn is_shared_dir(dir: &Path) -> bool {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = canonical(dir);
+    if dir.parent().is_none() {
+        return true;
+    }
+
+    let mut shared: Vec<PathBuf> = [
+        "USERPROFILE",
+        "APPDATA",
+        "LOCALAPPDATA",
+        "PUBLIC",
+        "SystemRoot",
+        "ProgramFiles",
+        "ProgramFiles(x86)",
+        "ProgramData",
+    ]
+    .iter()
+    .filter_map(env::var_os)
+    .map(PathBuf::from)
+    .collect();
+    if let Some(user_dirs) = directories_next::UserDirs::new() {
+        shared.push(user_dirs.home_dir().to_path_buf());
+        shared.extend(
+            [
+                user_dirs.desktop_dir(),
+                user_dirs.document_dir(),
+                user_dirs.download_dir(),
+                user_dirs.audio_dir(),
+                user_dirs.picture_dir(),
+                user_dirs.video_dir(),
+            ]
+            .iter()
+            .flatten()
+            .map(|user_dir| user_dir.to_path_buf()),
+        );
+    }
+    shared
+        .iter()
+        .any(|shared_dir| canonical(shared_dir).starts_with(&dir))
+}
+
+// creates the data directory if needed, locked-down profiles can have it exist but refuse writes
+pub fn ensure_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_test(dir)
+}
+
+// makes this and every later run portable, only takes effect once the launcher is restarted
+pub fn enable_portable() -> Result<()> {
+    if env::var_os("APPLAUNCHER_DATA_DIR")
+        .filter(|dir| !dir.is_empty())
+        .is_some()
+    {
+        return Err(eyre!(
+            "APPLAUNCHER_DATA_DIR is set and takes precedence over portable mode."
+        ));
+    }
+
+    let exe_dir =
+        exe_dir().ok_or_else(|| eyre!("The launcher's own directory could not be found."))?;
+    let sentinel = exe_dir.join(PORTABLE_SENTINEL);
+    fs::write(&sentinel, b"").map_err(|e| {
+        eyre!(
+            "The launcher's own directory {} can't be written to either ({}).",
+            exe_dir.display(),
+            e
+        )
+    })?;
+
+    Ok(())
+}
+
+// whether the data directory was chosen with APPLAUNCHER_DATA_DIR
+pub fn data_dir_from_env() -> bool {
+    BASE_DIRS
+        .as_ref()
+        .map(|base_dirs| base_dirs.data_from_env)
+        .unwrap_or(false)
+}
+
+// whether portable mode was asked for, and whether it's actually in effect
+pub fn portable() -> (bool, bool) {
+    BASE_DIRS
+        .as_ref()
+        .map(|base_dirs| (base_dirs.portable_requested, base_dirs.portable))
+        .unwrap_or((false, false))
+}
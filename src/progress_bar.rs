@@ -0,0 +1,28 @@
+use iui::controls::Control;
+use iui::UI;
+use ui_sys::{self, uiControl, uiProgressBar};
+
+// iui doesn't wrap libui's progress bar, so this is a minimal stand-in
+#[derive(Clone)]
+pub struct ProgressBar {
+    ui_progress_bar: *mut uiProgressBar,
+}
+
+impl ProgressBar {
+    pub fn new(_ctx: &UI) -> ProgressBar {
+        ProgressBar {
+            ui_progress_bar: unsafe { ui_sys::uiNewProgressBar() },
+        }
+    }
+
+    // value is a percentage from 0 to 100
+    pub fn set_value(&mut self, _ctx: &UI, value: i32) {
+        unsafe { ui_sys::uiProgressBarSetValue(self.ui_progress_bar, value) }
+    }
+}
+
+impl From<ProgressBar> for Control {
+    fn from(progress_bar: ProgressBar) -> Control {
+        unsafe { Control::from_ui_control(progress_bar.ui_progress_bar as *mut uiControl) }
+    }
+}
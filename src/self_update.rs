@@ -0,0 +1,37 @@
+use semver::Version;
+
+use crate::settings::ReleaseChannel;
+
+// which launcher releases count as an update:
+//
+// - build metadata (the part after a +) never makes a difference, 0.1.4+win and 0.1.4 are
+//   the same release
+// - pre-releases (0.2.0-beta.1) are only offered on the beta channel, the stable channel
+//   ignores them even when they're newer than what's running
+// - either channel is offered a stable release newer than what's running, so someone on a
+//   beta of 0.2.0 is told about 0.2.0 itself, which semver puts after all of its pre-releases
+// - nothing is ever offered as an update that isn't newer, switching from beta back to
+//   stable waits for the next stable release instead of downgrading
+pub fn is_update(current: &Version, candidate: &Version, channel: ReleaseChannel) -> bool {
+    if candidate.is_prerelease() && channel == ReleaseChannel::Stable {
+        return false;
+    }
+
+    // semver already leaves build metadata out of its ordering
+    candidate > current
+}
+
+// release tags are the version with a v in front, e.g. v0.1.4
+pub fn parse_tag(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    Version::parse(tag.strip_prefix("v").unwrap_or(tag)).ok()
+}
+
+// release assets are named like applauncher-v0.1.4-windows-x64.zip, one that names Windows as
+// one of its words is for this platform, which a darwin or linux build never does even though
+// "darwin" has a "win" in it
+pub fn is_windows_asset(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| matches!(word, "win" | "windows" | "win32" | "win64"))
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::io::{self, prelude::*, BufReader};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{unbounded, Receiver};
+
+// the running launcher listens on a loopback port it writes here, a second launcher started
+// through a link hands the link over instead of starting another update
+const PORT_FILE: &str = "instance.port";
+
+// whatever answers on the port has to say this back, a stale port file could point anywhere
+const ACK: &str = "applauncher-ok";
+
+const FORWARD_TIMEOUT_SECS: u64 = 2;
+// well under the forwarding side's timeout, so a link queued behind a client that says nothing
+// still gets through
+const LISTEN_TIMEOUT_MILLIS: u64 = 500;
+
+// hands a link to the launcher that's already running, false if there is none to take it
+pub fn forward(data_dir: &Path, uri: &str) -> bool {
+    let port: u16 = match fs::read_to_string(data_dir.join(PORT_FILE))
+        .ok()
+        .and_then(|port| port.trim().parse().ok())
+    {
+        Some(port) => port,
+        None => return false,
+    };
+
+    let forwarded = (|| -> io::Result<bool> {
+        let timeout = Duration::from_secs(FORWARD_TIMEOUT_SECS);
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        writeln!(stream, "{}", uri)?;
+
+        let mut ack = String::new();
+        BufReader::new(stream).read_line(&mut ack)?;
+        Ok(ack.trim_end() == ACK)
+    })();
+
+    match forwarded {
+        Ok(forwarded) => forwarded,
+        Err(e) => {
+            log::info!("no running launcher to forward the link to: {}", e);
+            false
+        }
+    }
+}
+
+// takes links from launchers started after this one, for as long as this one runs
+pub fn listen(data_dir: &Path) -> io::Result<Receiver<String>> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    fs::write(
+        data_dir.join(PORT_FILE),
+        listener.local_addr()?.port().to_string(),
+    )?;
+
+    let (send_uri, recv_uri) = unbounded();
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // one client that connects and says nothing mustn't hold up every link after it
+            let timeout = Duration::from_millis(LISTEN_TIMEOUT_MILLIS);
+            if stream.set_read_timeout(Some(timeout)).is_err() {
+                continue;
+            }
+            let mut uri = String::new();
+            if BufReader::new(&stream).read_line(&mut uri).is_err() {
+                continue;
+            }
+            let _ = writeln!(stream, "{}", ACK);
+            if send_uri.send(uri.trim_end().to_string()).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(recv_uri)
+}
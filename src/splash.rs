@@ -0,0 +1,147 @@
+use std::fs;
+use std::os::raw::c_int;
+use std::ptr;
+
+use eyre::{eyre, Result};
+use iui::controls::Control;
+use iui::UI;
+use ui_sys::{
+    self, uiArea, uiAreaDrawParams, uiAreaHandler, uiAreaKeyEvent, uiAreaMouseEvent, uiControl,
+    uiDrawBrush, uiDrawBrushType, uiDrawFillMode,
+};
+
+use applauncher::paths;
+
+// branding shown above the status labels, deployments without it just don't bundle the file
+pub const SPLASH_PATH: &str = "splash.png";
+
+// a decoded image, kept as 8-bit RGBA whatever the file was stored as
+pub struct Splash {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+// None when no splash is bundled, an error only when one is there but can't be read
+pub fn load() -> Result<Option<Splash>> {
+    let splash_path = paths::bundled_path(SPLASH_PATH);
+    if !splash_path.exists() {
+        return Ok(None);
+    }
+
+    let mut decoder = png::Decoder::new(fs::File::open(&splash_path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf)?;
+
+    let pixels: Vec<[u8; 4]> = match info.color_type {
+        png::ColorType::RGBA => buf.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]).collect(),
+        png::ColorType::RGB => buf.chunks(3).map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks(2).map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().map(|&p| [p, p, p, 255]).collect(),
+        color_type => return Err(eyre!("unsupported splash color type {:?}", color_type)),
+    };
+
+    Ok(Some(Splash {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    }))
+}
+
+// iui doesn't expose libui's drawing area, and libui can't draw images anyway, so the splash
+// is painted straight through ui-sys as runs of same colored pixels
+#[repr(C)]
+struct SplashHandler {
+    // has to come first, libui hands this pointer back to the callbacks
+    ui_area_handler: uiAreaHandler,
+    splash: Splash,
+}
+
+extern "C" fn draw(
+    handler: *mut uiAreaHandler,
+    _area: *mut uiArea,
+    draw_params: *mut uiAreaDrawParams,
+) {
+    let (splash, draw_params) =
+        unsafe { (&(*(handler as *mut SplashHandler)).splash, &*draw_params) };
+    if splash.width == 0 || splash.height == 0 {
+        return;
+    }
+
+    // shrink to fit, but never blow the image up past its own size, and keep it centered
+    let scale = (draw_params.AreaWidth / splash.width as f64)
+        .min(draw_params.AreaHeight / splash.height as f64)
+        .min(1.0);
+    let offset_x = (draw_params.AreaWidth - splash.width as f64 * scale) / 2.0;
+    let offset_y = (draw_params.AreaHeight - splash.height as f64 * scale) / 2.0;
+
+    for (y, row) in splash.pixels.chunks(splash.width).enumerate() {
+        let mut run_start = 0;
+        for x in 1..=row.len() {
+            if x < row.len() && row[x] == row[run_start] {
+                continue;
+            }
+
+            let [r, g, b, a] = row[run_start];
+            if a > 0 {
+                let mut brush = uiDrawBrush {
+                    Type: uiDrawBrushType::Solid,
+                    R: r as f64 / 255.0,
+                    G: g as f64 / 255.0,
+                    B: b as f64 / 255.0,
+                    A: a as f64 / 255.0,
+                    X0: 0.0,
+                    Y0: 0.0,
+                    X1: 0.0,
+                    Y1: 0.0,
+                    OuterRadius: 0.0,
+                    Stops: ptr::null_mut(),
+                    NumStops: 0,
+                };
+                unsafe {
+                    let path = ui_sys::uiDrawNewPath(uiDrawFillMode::Winding);
+                    ui_sys::uiDrawPathAddRectangle(
+                        path,
+                        offset_x + run_start as f64 * scale,
+                        offset_y + y as f64 * scale,
+                        (x - run_start) as f64 * scale,
+                        scale,
+                    );
+                    ui_sys::uiDrawPathEnd(path);
+                    ui_sys::uiDrawFill(draw_params.Context, path, &mut brush);
+                    ui_sys::uiDrawFreePath(path);
+                }
+            }
+            run_start = x;
+        }
+    }
+}
+
+// the splash doesn't react to any input
+extern "C" fn mouse_event(_: *mut uiAreaHandler, _: *mut uiArea, _: *mut uiAreaMouseEvent) {}
+extern "C" fn mouse_crossed(_: *mut uiAreaHandler, _: *mut uiArea, _: c_int) {}
+extern "C" fn drag_broken(_: *mut uiAreaHandler, _: *mut uiArea) {}
+extern "C" fn key_event(_: *mut uiAreaHandler, _: *mut uiArea, _: *mut uiAreaKeyEvent) -> c_int {
+    0
+}
+
+// the handler has to outlive the area, which lives as long as the window, so it's leaked
+pub fn area(_ctx: &UI, splash: Splash) -> Control {
+    let handler = Box::into_raw(Box::new(SplashHandler {
+        ui_area_handler: uiAreaHandler {
+            Draw: draw,
+            MouseEvent: mouse_event,
+            MouseCrossed: mouse_crossed,
+            DragBroken: drag_broken,
+            KeyEvent: key_event,
+        },
+        splash,
+    }));
+
+    unsafe {
+        let ui_area = ui_sys::uiNewArea(handler as *mut uiAreaHandler);
+        Control::from_ui_control(ui_area as *mut uiControl)
+    }
+}
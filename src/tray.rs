@@ -0,0 +1,252 @@
+// while the launcher stays around after the game started, it sits in the notification area
+// instead of keeping its window up for as long as the game runs. clicking the icon brings the
+// window back. in resident mode the icon is there from the start, minimizing goes to it and
+// right clicking it opens a menu, whose picks the event loop collects with take_action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrayAction {
+    Play,
+    CheckForUpdates,
+    OpenFolder,
+    Settings,
+    Quit,
+}
+
+#[cfg(windows)]
+const ACTIONS: [(TrayAction, &str); 5] = [
+    (TrayAction::Play, "Play"),
+    (TrayAction::CheckForUpdates, "Check for Updates"),
+    (TrayAction::OpenFolder, "Open Folder"),
+    (TrayAction::Settings, "Settings"),
+    (TrayAction::Quit, "Quit"),
+];
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::OsStr;
+    use std::iter;
+    use std::mem;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use iui::controls::Window;
+    use lazy_static::lazy_static;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::{HWND, POINT};
+    use winapi::um::shellapi::{
+        Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+    };
+    use winapi::um::winuser::{
+        AppendMenuW, CallWindowProcW, CreatePopupMenu, DestroyMenu, GetCursorPos, LoadIconW,
+        PostMessageW, RegisterWindowMessageW, SetForegroundWindow, SetWindowLongPtrW, ShowWindow,
+        TrackPopupMenu, GWLP_WNDPROC, IDI_APPLICATION, MF_SEPARATOR, MF_STRING, SIZE_MINIMIZED,
+        SW_HIDE, SW_MINIMIZE, SW_RESTORE, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_APP, WM_LBUTTONDBLCLK,
+        WM_LBUTTONUP, WM_NULL, WM_RBUTTONUP, WM_SIZE, WNDPROC,
+    };
+
+    use super::{TrayAction, ACTIONS};
+
+    // what the notification area sends the window when the icon is clicked
+    const WM_TRAY: UINT = WM_APP + 1;
+    const TRAY_ID: UINT = 1;
+
+    lazy_static! {
+        // kept for putting the icon back up after explorer restarted
+        static ref TOOLTIP: Mutex<String> = Mutex::new(String::new());
+    }
+
+    // libui's own window procedure, everything but the icon's messages still goes to it
+    static ORIGINAL_WNDPROC: AtomicIsize = AtomicIsize::new(0);
+    static TRAY_HWND: AtomicUsize = AtomicUsize::new(0);
+    static WITH_MENU: AtomicBool = AtomicBool::new(false);
+    // sent to every window when explorer restarts, which takes every icon with it
+    static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+    // the menu item picked last, counting from 1 so 0 is none
+    static PICKED: AtomicUsize = AtomicUsize::new(0);
+
+    fn wide(text: &str) -> Vec<u16> {
+        OsStr::new(text)
+            .encode_wide()
+            .chain(iter::once(0))
+            .collect()
+    }
+
+    fn handle(window: &Window) -> HWND {
+        unsafe { ui_sys::uiControlHandle(window.ptr() as *mut ui_sys::uiControl) as HWND }
+    }
+
+    fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+        let mut icon_data: NOTIFYICONDATAW = unsafe { mem::zeroed() };
+        icon_data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        icon_data.hWnd = hwnd;
+        icon_data.uID = TRAY_ID;
+        icon_data
+    }
+
+    fn add_icon(hwnd: HWND) -> bool {
+        let mut icon_data = icon_data(hwnd);
+        icon_data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+        icon_data.uCallbackMessage = WM_TRAY;
+        icon_data.hIcon = unsafe { LoadIconW(ptr::null_mut(), IDI_APPLICATION) };
+        // the tooltip is cut off at 127 characters and has to end in a null
+        let tooltip = TOOLTIP.lock().unwrap();
+        for (tip, c) in icon_data
+            .szTip
+            .iter_mut()
+            .zip(tooltip.encode_utf16().take(127))
+        {
+            *tip = c;
+        }
+
+        unsafe { Shell_NotifyIconW(NIM_ADD, &mut icon_data) != 0 }
+    }
+
+    // the window has to be in the foreground while the menu is up, or it won't close when
+    // clicking somewhere else
+    unsafe fn show_menu(hwnd: HWND) {
+        let menu = CreatePopupMenu();
+        for (id, (action, label)) in ACTIONS.iter().enumerate() {
+            if *action == TrayAction::Quit {
+                AppendMenuW(menu, MF_SEPARATOR, 0, ptr::null());
+            }
+            AppendMenuW(menu, MF_STRING, id + 1, wide(label).as_ptr());
+        }
+
+        let mut cursor = POINT { x: 0, y: 0 };
+        GetCursorPos(&mut cursor);
+        SetForegroundWindow(hwnd);
+        let picked = TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON,
+            cursor.x,
+            cursor.y,
+            0,
+            hwnd,
+            ptr::null(),
+        );
+        PostMessageW(hwnd, WM_NULL, 0, 0);
+        DestroyMenu(menu);
+
+        if picked > 0 {
+            PICKED.store(picked as usize, Ordering::SeqCst);
+        }
+    }
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        let with_menu = WITH_MENU.load(Ordering::SeqCst);
+        match (msg, lparam as UINT) {
+            (WM_TRAY, WM_LBUTTONUP) | (WM_TRAY, WM_LBUTTONDBLCLK) => {
+                ShowWindow(hwnd, SW_RESTORE);
+                SetForegroundWindow(hwnd);
+                return 0;
+            }
+            (WM_TRAY, WM_RBUTTONUP) if with_menu => {
+                show_menu(hwnd);
+                return 0;
+            }
+            (WM_SIZE, _) if with_menu && wparam == SIZE_MINIMIZED => {
+                ShowWindow(hwnd, SW_HIDE);
+                return 0;
+            }
+            _ if msg != 0 && msg == TASKBAR_CREATED.load(Ordering::SeqCst) => {
+                log::info!("the taskbar was recreated, putting the icon back");
+                add_icon(hwnd);
+            }
+            _ => {}
+        }
+
+        let original: WNDPROC = mem::transmute(ORIGINAL_WNDPROC.load(Ordering::SeqCst));
+        CallWindowProcW(original, hwnd, msg, wparam, lparam)
+    }
+
+    // puts the icon up, along with the menu in resident mode. false without a notification
+    // area, e.g. while explorer isn't running
+    pub fn add(window: &Window, tooltip: &str, with_menu: bool) -> bool {
+        WITH_MENU.fetch_or(with_menu, Ordering::SeqCst);
+        if TRAY_HWND.load(Ordering::SeqCst) != 0 {
+            return true;
+        }
+
+        let hwnd = handle(window);
+        *TOOLTIP.lock().unwrap() = tooltip.to_string();
+        unsafe {
+            TASKBAR_CREATED.store(
+                RegisterWindowMessageW(wide("TaskbarCreated").as_ptr()),
+                Ordering::SeqCst,
+            );
+        }
+        if !add_icon(hwnd) {
+            log::warn!("could not add an icon to the notification area");
+            return false;
+        }
+
+        unsafe {
+            let original = SetWindowLongPtrW(hwnd, GWLP_WNDPROC, window_proc as *const () as isize);
+            ORIGINAL_WNDPROC.store(original, Ordering::SeqCst);
+        }
+        TRAY_HWND.store(hwnd as usize, Ordering::SeqCst);
+        true
+    }
+
+    // hides the window away in the icon, or in the taskbar when there can't be one
+    pub fn minimize(window: &Window, tooltip: &str) {
+        let shown = if add(window, tooltip, false) {
+            SW_HIDE
+        } else {
+            SW_MINIMIZE
+        };
+        unsafe { ShowWindow(handle(window), shown) };
+    }
+
+    pub fn restore(window: &Window) {
+        unsafe {
+            ShowWindow(handle(window), SW_RESTORE);
+            SetForegroundWindow(handle(window));
+        }
+    }
+
+    pub fn take_action() -> Option<TrayAction> {
+        match PICKED.swap(0, Ordering::SeqCst) {
+            0 => None,
+            picked => ACTIONS.get(picked - 1).map(|(action, _)| *action),
+        }
+    }
+
+    // windows only notices an icon is stale once the mouse goes over it, so remove it before
+    // exiting
+    pub fn remove() {
+        let hwnd = TRAY_HWND.swap(0, Ordering::SeqCst);
+        if hwnd != 0 {
+            let mut icon_data = icon_data(hwnd as HWND);
+            unsafe { Shell_NotifyIconW(NIM_DELETE, &mut icon_data) };
+        }
+    }
+}
+
+#[cfg(windows)]
+pub use imp::{add, minimize, remove, restore, take_action};
+
+#[cfg(not(windows))]
+pub fn add(_window: &iui::controls::Window, _tooltip: &str, _with_menu: bool) -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn minimize(_window: &iui::controls::Window, _tooltip: &str) {}
+
+#[cfg(not(windows))]
+pub fn restore(_window: &iui::controls::Window) {}
+
+#[cfg(not(windows))]
+pub fn take_action() -> Option<TrayAction> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn remove() {}
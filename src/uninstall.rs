@@ -0,0 +1,87 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eyre::{eyre, Result};
+
+use crate::backups;
+use crate::install_lock::{self, Owner};
+use crate::manifest::{self, InstallManifest};
+use crate::paths;
+use crate::settings::LaunchTarget;
+
+// ERROR_SHARING_VIOLATION, what windows answers deleting a file another process has open
+const SHARING_VIOLATION: i32 = 32;
+
+fn in_use(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::PermissionDenied || e.raw_os_error() == Some(SHARING_VIOLATION)
+}
+
+// removes the game's install directory as a whole, along with its backups, and only then
+// forgets it in the manifest so a removal that stops halfway can still be repaired or retried.
+// returns the directory that was removed
+pub fn uninstall(data_dir: &Path, app: &str, launch_targets: &[LaunchTarget]) -> Result<PathBuf> {
+    let mut manifest = InstallManifest::load(data_dir)?;
+    let install_dir = manifest
+        .game(app)
+        .map(|entry| entry.dir.clone())
+        .ok_or_else(|| eyre!("{} is not installed.", app))?;
+
+    if install_dir.exists() {
+        // everything in the directory goes, so it has to be the game's own. a hand edited
+        // manifest or a game put straight into a user folder would take the user's files
+        // along, and with --json --yes nobody is there to notice
+        if paths::is_shared_dir(&install_dir) {
+            return Err(eyre!(
+                "{} holds more than the game, please remove the game's files by hand.",
+                install_dir.display()
+            ));
+        }
+        if !paths::looks_installed(&install_dir, launch_targets) {
+            return Err(eyre!(
+                "The game does not seem to be installed in {}, it was left as it is.",
+                install_dir.display()
+            ));
+        }
+
+        if let Some(lock_info) = install_lock::holder(&install_dir) {
+            return Err(match lock_info.owner {
+                Owner::Game => eyre!("The game is running, please close it before uninstalling."),
+                Owner::Launcher => eyre!("Another AppLauncher is updating the game right now."),
+            });
+        }
+
+        // held until the directory is gone so no update starts in the meantime, the lock
+        // file goes along with everything else
+        let _install_lock = install_lock::acquire(&install_dir)?;
+        log::info!("removing {}", install_dir.display());
+        fs::remove_dir_all(&install_dir).map_err(|e| {
+            // windows won't delete files a process still has open, the game started without
+            // taking the lock or something else is looking at its files
+            if in_use(&e) {
+                eyre!(
+                    "Some files in {} are in use, please close the game and anything else using them and try again.",
+                    install_dir.display()
+                )
+            } else {
+                eyre!("{} could not be removed ({}).", install_dir.display(), e)
+            }
+        })?;
+    } else {
+        log::warn!(
+            "{} is already gone, only removing it from the manifest",
+            install_dir.display()
+        );
+    }
+
+    let backups_dir = backups::backups_dir(data_dir, &manifest::normalize_app_id(app));
+    if backups_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&backups_dir) {
+            log::warn!("could not remove {}: {}", backups_dir.display(), e);
+        }
+    }
+
+    manifest.games.remove(&manifest::normalize_app_id(app));
+    manifest.save(data_dir)?;
+    Ok(install_dir)
+}
@@ -0,0 +1,2973 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{thread, time};
+
+use chrono::{DateTime, Local, Utc};
+use crossbeam::channel::{bounded, unbounded, RecvTimeoutError, Sender};
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use md5::Md5;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use scopeguard::defer;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::digest::DynDigest;
+use sha2::Sha256;
+
+use crate::backups;
+use crate::butler;
+use crate::disk_space::{self, SpaceReservation};
+use crate::error::{Error, ErrorKind};
+use crate::install_lock;
+use crate::integrity;
+use crate::manifest::{self, AppEntry, InstallManifest};
+use crate::paths;
+use crate::self_update;
+use crate::settings::{ParamEncoding, RequestMethod, Settings};
+use crate::CURRENT_VERSION;
+
+// the proxy and allowed hosts a client was built with
+type ClientKey = (Option<String>, Vec<String>);
+
+lazy_static! {
+    // kept for the proxy and allowed hosts they were built with, so connections are reused
+    static ref HTTP_CLIENTS: Mutex<HashMap<ClientKey, reqwest::blocking::Client>> =
+        Mutex::new(HashMap::new());
+}
+
+// version of the update server API this launcher understands
+const API_VERSION: &str = "0";
+
+// the game and platform patches are asked for, and the only ones accepted
+const APP: &str = "unnamed-sdvx-clone";
+const PLATFORM: &str = "win32";
+
+// the patch list is small, anything slower than this is a connection that isn't going to work
+const CONTACT_SERVER_TIMEOUT_SECS: u64 = 15;
+
+// how long to wait on a 429 without a usable Retry-After, the most to wait and how often
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+const RATE_LIMIT_RETRIES: usize = 5;
+
+// the health check is meant to be quick, a server this slow to answer counts as down
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+// only has to tell an offline machine apart, a connection that slow won't get far anyway
+const CONNECTIVITY_PROBE_TIMEOUT_MILLIS: u64 = 1500;
+// offline machines can take a while to give up on a name as well
+const NAME_LOOKUP_TIMEOUT_MILLIS: u64 = 3000;
+
+// error responses can be whole HTML pages, the start is enough to tell what went wrong
+const MAX_LOGGED_BODY_CHARS: usize = 512;
+
+// as many redirects as reqwest follows on its own
+const MAX_REDIRECTS: usize = 10;
+
+// patch sets are often many small files on the same host, so keep connections around
+// and prefer HTTP/2, which rustls negotiates through ALPN, to multiplex them over one
+fn build_http_client(proxy: Option<&str>, allowed_hosts: Vec<String>) -> reqwest::blocking::Client {
+    // lets the server tell which launcher versions are out there, overridable for testing
+    let user_agent = env::var("APPLAUNCHER_USER_AGENT")
+        .unwrap_or_else(|_| format!("OrchestraFM-AppLauncher/{}", CURRENT_VERSION));
+
+    let builder = reqwest::blocking::Client::builder()
+        .user_agent(user_agent)
+        .pool_max_idle_per_host(8)
+        .pool_idle_timeout(time::Duration::from_secs(90));
+
+    // native TLS is stuck on HTTP/1.1 but uses the system certificate store, which
+    // is what works behind TLS intercepting proxies
+    let builder = if env::var_os("APPLAUNCHER_HTTP1").is_some() {
+        builder.use_native_tls()
+    } else {
+        builder.use_rustls_tls()
+    };
+
+    let builder = match proxy.and_then(parse_proxy) {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    };
+
+    // a patch that passed the host check could otherwise still be served from anywhere
+    let builder = builder.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+        let host = attempt.url().host_str().unwrap_or_default().to_string();
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if host_allowed(&host, &allowed_hosts) {
+            attempt.follow()
+        } else {
+            log::error!(
+                "refusing the redirect to {}, {} is not one of the allowed hosts ({})",
+                attempt.url(),
+                host,
+                allowed_hosts.join(", ")
+            );
+            attempt.error(format!(
+                "redirected to {}, which is not an allowed host",
+                host
+            ))
+        }
+    }));
+
+    builder.build().expect("HTTP client failed to initialize.")
+}
+
+// the GitHub client is built apart from these, it goes through the same proxy
+pub fn parse_proxy(proxy: &str) -> Option<reqwest::Proxy> {
+    match reqwest::Proxy::all(proxy) {
+        Ok(proxy) => Some(proxy),
+        Err(e) => {
+            log::error!("ignoring the proxy, it is not a valid URL: {}", e);
+            None
+        }
+    }
+}
+
+fn http_client(settings: &Settings) -> reqwest::blocking::Client {
+    let proxy = settings.proxy();
+    let allowed_hosts = settings.allowed_hosts();
+    HTTP_CLIENTS
+        .lock()
+        .unwrap()
+        .entry((proxy.clone(), allowed_hosts.clone()))
+        .or_insert_with(|| build_http_client(proxy.as_deref(), allowed_hosts))
+        .clone()
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchInfo {
+    pub id: u64,
+    pub app: String,
+    pub name: String,
+    pub platform: String,
+    pub issuer: i64,
+    pub url: String,
+    pub hash: u32,
+    pub sig: String,
+    #[serde(rename = "sig_hash")]
+    pub sig_hash: u32,
+    pub arch: String,
+    // size of the patch file in bytes, older servers don't send it
+    #[serde(default)]
+    pub size: Option<u64>,
+    // patches that have to be applied before this one, e.g. a base pack
+    #[serde(default)]
+    pub requires: Vec<u64>,
+    // what the file at `sig` is, older servers only have wharf signatures
+    #[serde(rename = "sig_format", default)]
+    pub sig_format: SigFormat,
+}
+
+// wharf is butler's signature of the files a patch results in, butler checks what it applied
+// against it. minisign is a signature of the patch file itself, made with the publisher's key
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigFormat {
+    Wharf,
+    Minisign,
+}
+
+impl Default for SigFormat {
+    fn default() -> Self {
+        SigFormat::Wharf
+    }
+}
+
+// the patch list comes either bare or, from servers that want to retire old launchers, along
+// with the oldest launcher version they still serve
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatchListResponse {
+    Bare(Vec<PatchInfo>),
+    Gated {
+        patches: Vec<PatchInfo>,
+        min_client_version: Option<String>,
+    },
+}
+
+// every file an install at some patch is made of, for checking one file by file
+#[derive(Deserialize)]
+struct FileManifestResponse {
+    files: BTreeMap<String, integrity::FileChecksum>,
+}
+
+// the update server refuses launchers older than this, whatever GitHub has to say about it
+#[derive(Debug)]
+pub struct ClientTooOld {
+    pub required: Version,
+}
+
+impl fmt::Display for ClientTooOld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The update server requires AppLauncher v{} or newer. Please update to the latest version of the AppLauncher.",
+            self.required
+        )
+    }
+}
+
+impl std::error::Error for ClientTooOld {}
+
+// the update server is down for maintenance on purpose, the installed game can still be played
+#[derive(Debug)]
+pub struct Maintenance {
+    // when it's expected to be over, in local time if the server's answer could be read as a time
+    pub until: Option<String>,
+}
+
+impl fmt::Display for Maintenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The update servers are under maintenance, please try again later."
+        )?;
+        if let Some(until) = self.until.as_ref() {
+            write!(f, " It should be over by {}.", until)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Maintenance {}
+
+// servers say they're under maintenance with a 503, a flag in what they answer with or both
+#[derive(Deserialize)]
+struct MaintenanceNotice {
+    #[serde(default)]
+    maintenance: bool,
+    #[serde(default, alias = "maintenance_until")]
+    until: Option<String>,
+}
+
+// an RFC 3339 time, an HTTP date or seconds from now as in Retry-After
+fn local_time(time: &str) -> String {
+    let time = time.trim();
+    let parsed = DateTime::parse_from_rfc3339(time)
+        .or_else(|_| DateTime::parse_from_rfc2822(time))
+        .map(|time| time.with_timezone(&Local))
+        .ok()
+        .or_else(|| {
+            let secs = time.parse().ok()?;
+            Some(Local::now() + chrono::Duration::seconds(secs))
+        });
+    match parsed {
+        Some(parsed) => parsed.format("%Y-%m-%d %H:%M").to_string(),
+        None => time.to_string(),
+    }
+}
+
+fn maintenance(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<Maintenance> {
+    let notice = serde_json::from_str::<MaintenanceNotice>(body).ok();
+    let flagged = matches!(&notice, Some(notice) if notice.maintenance);
+    let retry_after = headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|retry_after| retry_after.to_str().ok());
+    // any other 503 is the server being down rather than taken down
+    let unavailable = status == StatusCode::SERVICE_UNAVAILABLE
+        && (flagged || retry_after.is_some() || body.to_lowercase().contains("maintenance"));
+    if !flagged && !unavailable {
+        return None;
+    }
+
+    let until = notice
+        .and_then(|notice| notice.until)
+        .or_else(|| retry_after.map(str::to_string));
+    log::warn!(
+        "update server is under maintenance until {}",
+        until.as_deref().unwrap_or("further notice")
+    );
+    Some(Maintenance {
+        until: until.as_deref().map(local_time),
+    })
+}
+
+// a download stopped halfway because the update was cancelled, the caller is expected to
+// check the cancel token rather than report it
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The update was cancelled.")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+// messages from the helper thread to the user interface, or to stdout as JSON with --json
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum WorkerMsg {
+    // a new step has started
+    Status(String),
+    // how much of the whole update is done, from 0.0 to 1.0
+    Progress(f64),
+    // how many bytes the update is going to download
+    DownloadSize(u64),
+    // how many bytes just arrived, for working out the download speed
+    Downloaded(u64),
+    // what kind of failure the error that follows is, only sent for those that were classified
+    ErrorKind(ErrorKind),
+    // the update failed and nothing else will follow
+    Error(String),
+    // every patch has been applied
+    Done,
+}
+
+// tasks report how far along they are in units, so long running tasks like applying can
+// move the progress bar along before they finish
+const TASK_UNITS: u64 = 1000;
+
+// how much of the progress bar each task of a patch takes up, roughly the bytes it has to get
+// through, checksums only read a file back so they are a lot cheaper than downloading it
+#[derive(Clone, Copy)]
+pub struct TaskWeights {
+    download: u64,
+    signature: u64,
+    apply: u64,
+}
+
+// signatures are small next to their patch, but their size isn't known up front
+const SIGNATURE_SHARE: u64 = 100;
+const CHECKSUM_SHARE: u64 = 10;
+
+impl TaskWeights {
+    // every weight is at least 1, so with nothing known all tasks count the same
+    fn for_size(size: u64) -> TaskWeights {
+        TaskWeights {
+            download: size.max(1),
+            signature: (size / SIGNATURE_SHARE).max(1),
+            apply: size.max(1),
+        }
+    }
+
+    fn checksum(weight: u64) -> u64 {
+        (weight / CHECKSUM_SHARE).max(1)
+    }
+
+    fn total(&self) -> u64 {
+        self.download
+            + self.signature
+            + TaskWeights::checksum(self.download)
+            + TaskWeights::checksum(self.signature)
+            + self.apply
+    }
+}
+
+// patches of unknown size are taken to be as big as the average known one, so a missing size
+// can't throw the bar off by more than the patch itself
+pub fn task_weights(patch_sizes: &[Option<u64>]) -> Vec<TaskWeights> {
+    let known_sizes: Vec<u64> = patch_sizes.iter().flatten().copied().collect();
+    let fallback_size = if known_sizes.is_empty() {
+        0
+    } else {
+        known_sizes.iter().sum::<u64>() / known_sizes.len() as u64
+    };
+
+    patch_sizes
+        .iter()
+        .map(|size| TaskWeights::for_size(size.unwrap_or(fallback_size)))
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct TaskProgress {
+    send_state: Sender<WorkerMsg>,
+    started: Arc<AtomicUsize>,
+    finished_weight: Arc<AtomicU64>,
+    // grows when a patch has to be fetched again, every clone has to see that
+    total_tasks: Arc<AtomicUsize>,
+    total_weight: u64,
+}
+
+impl TaskProgress {
+    // every patch is five tasks, downloading the patch and signature, checking both and applying
+    pub fn new(send_state: Sender<WorkerMsg>, weights: &[TaskWeights]) -> TaskProgress {
+        TaskProgress {
+            send_state,
+            started: Arc::new(AtomicUsize::new(0)),
+            finished_weight: Arc::new(AtomicU64::new(0)),
+            total_tasks: Arc::new(AtomicUsize::new(weights.len() * 5)),
+            total_weight: weights.iter().map(TaskWeights::total).sum(),
+        }
+    }
+
+    // downloading ahead of time leaves applying for later, so every patch is four tasks
+    fn without_applying(send_state: Sender<WorkerMsg>, weights: &[TaskWeights]) -> TaskProgress {
+        let weights: Vec<TaskWeights> = weights
+            .iter()
+            .map(|weights| TaskWeights {
+                apply: 0,
+                ..*weights
+            })
+            .collect();
+        TaskProgress {
+            total_tasks: Arc::new(AtomicUsize::new(weights.len() * 4)),
+            ..TaskProgress::new(send_state, &weights)
+        }
+    }
+
+    // picks the count and the bar up where an interrupted update left them
+    fn resumed(self, resumed: &UpdateProgress) -> TaskProgress {
+        TaskProgress {
+            started: Arc::new(AtomicUsize::new(resumed.applied * 5)),
+            finished_weight: Arc::new(AtomicU64::new(resumed.weight_done)),
+            total_tasks: Arc::new(AtomicUsize::new(
+                self.total_tasks.load(Ordering::SeqCst) + resumed.applied * 5,
+            )),
+            total_weight: self.total_weight + resumed.weight_done,
+            ..self
+        }
+    }
+
+    // shared between the fetch and apply threads, so the count stays in order either way
+    fn notify(&self, task: &str, weight: u64) -> Task {
+        let started = self.started.fetch_add(1, Ordering::SeqCst) + 1;
+        self.send_state
+            .send(WorkerMsg::Status(format!(
+                "{} ({}/{})...",
+                task,
+                started,
+                self.total_tasks.load(Ordering::SeqCst)
+            )))
+            .unwrap();
+
+        Task {
+            progress: self.clone(),
+            weight,
+            done_units: 0,
+            done_weight: 0,
+            unreported_bytes: 0,
+            reported: time::Instant::now(),
+        }
+    }
+
+    // fetching a patch again counts its downloads and checks once more, the bar stays where it
+    // is though, their weight was counted the first time
+    fn fetch_again(&self) -> TaskWeights {
+        self.total_tasks.fetch_add(4, Ordering::SeqCst);
+        TaskWeights {
+            download: 0,
+            signature: 0,
+            apply: 0,
+        }
+    }
+
+    // a task that doesn't move the bar, for work that was already counted
+    fn heartbeat(&self) -> Task {
+        Task {
+            progress: self.clone(),
+            weight: 0,
+            done_units: 0,
+            done_weight: 0,
+            unreported_bytes: 0,
+            reported: time::Instant::now(),
+        }
+    }
+
+    // advancing by 0 still tells the watchdog something is happening
+    fn advance(&self, weight: u64) {
+        let finished_weight = self.finished_weight.fetch_add(weight, Ordering::SeqCst) + weight;
+        self.send_state
+            .send(WorkerMsg::Progress(
+                (finished_weight as f64 / self.total_weight.max(1) as f64).min(1.0),
+            ))
+            .unwrap();
+    }
+}
+
+// how often a download tells the user interface how much arrived
+const DOWNLOAD_REPORT_INTERVAL: time::Duration = time::Duration::from_millis(250);
+
+pub struct Task {
+    progress: TaskProgress,
+    weight: u64,
+    done_units: u64,
+    done_weight: u64,
+    // what arrived since the user interface last heard, reads are too small to send each one
+    unreported_bytes: u64,
+    reported: time::Instant,
+}
+
+impl Task {
+    // only ever moves forward, a retry starting over doesn't take the bar back with it
+    pub fn set_done(&mut self, units: u64) {
+        let units = units.min(TASK_UNITS);
+        if units <= self.done_units {
+            return;
+        }
+
+        let done_weight = self.weight * units / TASK_UNITS;
+        self.progress.advance(done_weight - self.done_weight);
+        self.done_units = units;
+        self.done_weight = done_weight;
+    }
+
+    pub fn finish(&mut self) {
+        self.set_done(TASK_UNITS);
+        self.report_downloaded();
+    }
+
+    fn heartbeat(&self) {
+        self.progress.advance(0);
+    }
+
+    fn downloaded(&mut self, bytes: u64) {
+        self.unreported_bytes += bytes;
+        if self.reported.elapsed() >= DOWNLOAD_REPORT_INTERVAL {
+            self.report_downloaded();
+        }
+    }
+
+    fn report_downloaded(&mut self) {
+        if self.unreported_bytes > 0 {
+            self.progress
+                .send_state
+                .send(WorkerMsg::Downloaded(self.unreported_bytes))
+                .unwrap();
+        }
+        self.unreported_bytes = 0;
+        self.reported = time::Instant::now();
+    }
+}
+
+// lets the user interface stop or pause the helper thread, and whatever it spawned, from the
+// outside
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    // how many times downloads were paused, a download that breaks off after one is resumed
+    pauses: Arc<AtomicUsize>,
+    // the most downloads may read a second, 0 for as fast as they can
+    rate_limit: Arc<AtomicU64>,
+    child: Arc<Mutex<Option<process::Child>>>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    // stops the helper thread at the next opportunity without killing what it's running
+    pub fn stop(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // a retry starts over with the same token, only once whatever it stopped has returned
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    // downloads stop reading until resumed, butler can't be paused and carries on
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            self.pauses.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn throttle(&self, bytes_per_sec: u64) {
+        self.rate_limit.store(bytes_per_sec, Ordering::SeqCst);
+    }
+
+    fn rate_limit(&self) -> u64 {
+        self.rate_limit.load(Ordering::SeqCst)
+    }
+
+    fn pauses(&self) -> usize {
+        self.pauses.load(Ordering::SeqCst)
+    }
+
+    // keeps hold of a child process so cancelling can kill it
+    fn track(&self, mut child: process::Child) {
+        if self.is_cancelled() {
+            let _ = child.kill();
+        }
+        *self.child.lock().unwrap() = Some(child);
+    }
+
+    fn untrack(&self) -> Option<process::Child> {
+        self.child.lock().unwrap().take()
+    }
+}
+
+// a digest the server or its CDN sent along with a download, hashed as the bytes come in
+struct TransportDigest {
+    algorithm: &'static str,
+    expected: Vec<u8>,
+    hasher: Box<dyn DynDigest>,
+}
+
+impl TransportDigest {
+    // Content-MD5, or the Digest header in one of the algorithms known here, SHA-256 first
+    fn from_headers(headers: &HeaderMap) -> Option<TransportDigest> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let digests: Vec<(String, &str)> = header("Digest")
+            .into_iter()
+            .flat_map(|digest| digest.split(','))
+            .filter_map(|digest| {
+                let mut digest = digest.trim().splitn(2, '=');
+                Some((digest.next()?.to_lowercase(), digest.next()?))
+            })
+            .chain(header("Content-MD5").map(|md5| ("md5".to_string(), md5)))
+            .collect();
+
+        let find = |algorithm: &str| {
+            digests
+                .iter()
+                .find(|(name, _)| name == algorithm)
+                .and_then(|(_, value)| base64::decode(value.trim()).ok())
+        };
+        if let Some(expected) = find("sha-256") {
+            return Some(TransportDigest {
+                algorithm: "SHA-256",
+                expected,
+                hasher: Box::new(Sha256::default()),
+            });
+        }
+        find("md5").map(|expected| TransportDigest {
+            algorithm: "MD5",
+            expected,
+            hasher: Box::new(Md5::default()),
+        })
+    }
+
+    fn matches(self) -> bool {
+        *self.hasher.finalize() == *self.expected
+    }
+}
+
+// reports how much of a download has come in, which also tells the watchdog it isn't stuck
+struct ProgressReader<'a, R> {
+    inner: R,
+    task: Option<&'a mut Task>,
+    expected_len: Option<u64>,
+    read_len: u64,
+    digest: Option<TransportDigest>,
+    // whether it came over the network, local patches don't count towards the data used
+    metered: bool,
+}
+
+// everything downloaded since the launcher started, retries included
+static DOWNLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+pub fn downloaded_bytes() -> u64 {
+    DOWNLOADED_BYTES.load(Ordering::SeqCst)
+}
+
+// without a known length, let the user interface know something arrived every so often
+const HEARTBEAT_BYTES: u64 = 1024 * 1024;
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let previous_len = self.read_len;
+        self.read_len += read as u64;
+        if self.metered {
+            DOWNLOADED_BYTES.fetch_add(read as u64, Ordering::SeqCst);
+        }
+        if let Some(digest) = self.digest.as_mut() {
+            digest.hasher.update(&buf[..read]);
+        }
+
+        let task = match self.task.as_deref_mut() {
+            Some(task) => task,
+            None => return Ok(read),
+        };
+        if read > 0 {
+            task.downloaded(read as u64);
+        }
+        match self.expected_len {
+            Some(expected_len) if expected_len > 0 => {
+                task.set_done(self.read_len.min(expected_len) * TASK_UNITS / expected_len);
+            }
+            _ => {
+                if self.read_len / HEARTBEAT_BYTES != previous_len / HEARTBEAT_BYTES {
+                    task.heartbeat();
+                }
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+// removes anything the helper thread may have left behind in the working directory
+pub fn cleanup_temp_files(app: &str) {
+    // older launchers kept every game's files at the top
+    for dir in [paths::temp_dir(), app_temp_dir(app)].iter() {
+        if let Ok(dir_entries) = fs::read_dir(dir) {
+            for dir_entry in dir_entries.flatten() {
+                let file_name = dir_entry.file_name();
+                if file_name.to_string_lossy().starts_with("tmp-file-") {
+                    let _ = fs::remove_file(dir_entry.path());
+                }
+            }
+        }
+    }
+    let _ = fs::remove_dir_all(paths::temp_path("butler-workingdir"));
+}
+
+// games are updated and prefetched side by side, so each one keeps its temp files apart
+fn app_temp_dir(app: &str) -> PathBuf {
+    paths::temp_path(&manifest::normalize_app_id(app))
+}
+
+fn temp_patch_paths(patch: &PatchInfo) -> (PathBuf, PathBuf) {
+    let app_temp_dir = app_temp_dir(&patch.app);
+    (
+        app_temp_dir.join(format!("tmp-file-{}.pwr", patch.id)),
+        app_temp_dir.join(format!("tmp-file-{}.pwr.sig", patch.id)),
+    )
+}
+
+// deletes the file once dropped, even if that happens on another thread or in a channel
+pub struct TempFile {
+    pub path: PathBuf,
+}
+
+// set by --keep-temp or the keep_temp setting, keeps a copy of whatever failed for debugging
+pub static KEEP_TEMP: AtomicBool = AtomicBool::new(false);
+
+impl TempFile {
+    // copies the file into the debug folder when asked to, the original is still removed on drop
+    fn preserve(&self) {
+        if !KEEP_TEMP.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let debug_dir = paths::debug_dir();
+        let kept = match self.path.file_name() {
+            Some(file_name) => debug_dir.join(file_name),
+            None => return,
+        };
+        match fs::create_dir_all(&debug_dir).and_then(|_| fs::copy(&self.path, &kept)) {
+            Ok(_) => log::info!("kept {} for debugging", kept.display()),
+            Err(e) => log::warn!("failed to keep {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub struct FetchedPatch {
+    pub patch_file: TempFile,
+    pub sig_file: TempFile,
+    pub sig_format: SigFormat,
+    // whether each file matched the digest it was delivered with, if it came with one, for
+    // telling a badly packaged patch apart from one that was damaged on the way
+    patch_transport_check: Option<bool>,
+    sig_transport_check: Option<bool>,
+}
+
+pub fn fetch_patch_list(
+    settings: &Settings,
+    version: u16,
+    send_state: Option<&Sender<WorkerMsg>>,
+    cancel: Option<&CancelToken>,
+) -> Result<Vec<PatchInfo>> {
+    fetch_app_patch_list(settings, APP, version, send_state, cancel)
+}
+
+// the same server serves every game, other games in the manifest are asked for by their id
+fn fetch_app_patch_list(
+    settings: &Settings,
+    app: &str,
+    version: u16,
+    send_state: Option<&Sender<WorkerMsg>>,
+    cancel: Option<&CancelToken>,
+) -> Result<Vec<PatchInfo>> {
+    // always in the same order, so equal requests make equal URLs for caches to match up
+    let version = version.to_string();
+    let patch_resp_params = [
+        ("app", app),
+        ("platform", PLATFORM),
+        ("version", version.as_str()),
+    ];
+
+    let request = &settings.patch_list_request;
+    let patch_list_url = format!("{}{}", settings.server(), request.path);
+    let client = http_client(settings);
+    let patch_list_req = match request.method {
+        RequestMethod::Get => client.get(&patch_list_url),
+        RequestMethod::Post => client.post(&patch_list_url),
+    };
+    let patch_list_req = match request.params {
+        ParamEncoding::Form => patch_list_req.form(&patch_resp_params),
+        ParamEncoding::Query => patch_list_req.query(&patch_resp_params),
+    };
+
+    let patch_list_resp = patch_list_req
+        .header("X-App-Id", app)
+        .header("X-Platform", PLATFORM)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .header("X-Api-Version", API_VERSION)
+        .timeout(time::Duration::from_secs(CONTACT_SERVER_TIMEOUT_SECS));
+    let patch_list_resp = send_request(patch_list_resp, send_state, cancel)?;
+    let status = patch_list_resp.status();
+    let headers = patch_list_resp.headers().clone();
+    let patch_list_body = patch_list_resp.text()?;
+
+    if let Some(maintenance) = maintenance(status, &headers, &patch_list_body) {
+        return Err(maintenance.into());
+    }
+    if status.ne(&StatusCode::OK) {
+        log::error!("patch list request failed with {}", status);
+        return Err(
+            Error::network(format!("The update server returned an error ({}).", status)).into(),
+        );
+    }
+
+    // a server that moved on to an incompatible API has to be caught here, not mis-parsed below
+    match headers
+        .get("X-Api-Version")
+        .map(|version| version.to_str().unwrap_or_default().to_string())
+    {
+        Some(version) if version != API_VERSION => {
+            log::error!(
+                "update server speaks API version {}, expected {}",
+                version,
+                API_VERSION
+            );
+            return Err(eyre!(
+                "The update server is no longer compatible with this launcher. Please update to the latest version of the AppLauncher."
+            ));
+        }
+        Some(_) => {}
+        None => log::warn!("update server did not report an API version"),
+    }
+
+    let patch_list = serde_json::from_str::<PatchListResponse>(&patch_list_body).map_err(|e| {
+        log::error!(
+            "unexpected patch list response ({}): {}",
+            e,
+            patch_list_body
+                .chars()
+                .take(MAX_LOGGED_BODY_CHARS)
+                .collect::<String>()
+        );
+        eyre!("The update server returned an unexpected response.")
+    })?;
+
+    let patches = gate_patch_list(patch_list)?;
+    check_targets(&patches, app)?;
+    dedupe_patches(&patches)
+}
+
+// sizes and checksums of the files an install at `version` should have, which the server
+// only knows for patches it still serves
+pub fn fetch_file_manifest(
+    settings: &Settings,
+    version: u16,
+    cancel: Option<&CancelToken>,
+) -> Result<BTreeMap<String, integrity::FileChecksum>> {
+    let version = version.to_string();
+    let file_manifest_req = http_client(settings)
+        .get(&format!(
+            "{}{}",
+            settings.server(),
+            settings.file_manifest_path
+        ))
+        .query(&[
+            ("app", APP),
+            ("platform", PLATFORM),
+            ("version", version.as_str()),
+        ])
+        .header("X-App-Id", APP)
+        .header("X-Platform", PLATFORM)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .header("X-Api-Version", API_VERSION)
+        .timeout(time::Duration::from_secs(CONTACT_SERVER_TIMEOUT_SECS));
+    let file_manifest_resp = send_request(file_manifest_req, None, cancel)?;
+    let status = file_manifest_resp.status();
+    let headers = file_manifest_resp.headers().clone();
+    let file_manifest_body = file_manifest_resp.text()?;
+
+    if let Some(maintenance) = maintenance(status, &headers, &file_manifest_body) {
+        return Err(maintenance.into());
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(eyre!(
+            "The update server has no list of game files for patch {}.",
+            version
+        ));
+    }
+    if status.ne(&StatusCode::OK) {
+        log::error!("file manifest request failed with {}", status);
+        return Err(
+            Error::network(format!("The update server returned an error ({}).", status)).into(),
+        );
+    }
+
+    let file_manifest =
+        serde_json::from_str::<FileManifestResponse>(&file_manifest_body).map_err(|e| {
+            log::error!(
+                "unexpected file manifest response ({}): {}",
+                e,
+                file_manifest_body
+                    .chars()
+                    .take(MAX_LOGGED_BODY_CHARS)
+                    .collect::<String>()
+            );
+            eyre!("The update server returned an unexpected response.")
+        })?;
+    log::info!(
+        "the update server lists {} files for patch {}",
+        file_manifest.files.len(),
+        version
+    );
+    Ok(file_manifest.files)
+}
+
+// downloads just the files given over the ones in the install, for when only a few of them
+// went missing or got modified. every one needs a download of its own in the server's list,
+// otherwise the whole game has to be installed again
+pub fn repair_files(
+    install_dir: &Path,
+    checksums: &BTreeMap<String, integrity::FileChecksum>,
+    files: &[String],
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let allowed_hosts = settings.allowed_hosts();
+    let mut downloads = Vec::new();
+    for file in files {
+        let checksum = checksums
+            .get(file)
+            .filter(|_| integrity::is_inside_install(file))
+            .ok_or_else(|| eyre!("{} is not part of the game.", file))?;
+        let url = checksum
+            .url
+            .as_deref()
+            .ok_or_else(|| eyre!("The update server has no download for {}.", file))?;
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        match host {
+            Some(host) if host_allowed(&host, &allowed_hosts) => {}
+            _ => {
+                log::error!(
+                    "rejecting the download of {} from {}, its host is not one of {}",
+                    file,
+                    url,
+                    allowed_hosts.join(", ")
+                );
+                return Err(eyre!(
+                    "The update server sent a file from a location that isn't allowed."
+                ));
+            }
+        }
+        // the hash names the download's temp file, so it can't be allowed to be a path
+        if checksum.sha256.len() != 64 || !checksum.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            log::error!(
+                "rejecting the download of {}, {:?} is not a SHA-256 hash",
+                file,
+                checksum.sha256
+            );
+            return Err(eyre!("The update server returned an unexpected response."));
+        }
+        downloads.push((file, checksum, url));
+    }
+
+    let _install_lock = install_lock::acquire(install_dir)?;
+    for (file, checksum, url) in downloads {
+        log::info!("repairing {} from {}", file, url);
+        let temp_path = paths::temp_path(&format!("repair-{}", checksum.sha256));
+        download_to(url, &temp_path, DownloadProgress::Hidden, settings, cancel)?;
+        if !integrity::matches(&temp_path, checksum)? {
+            let _ = fs::remove_file(&temp_path);
+            log::error!("the download of {} does not match its checksum", file);
+            return Err(eyre!("The download of {} was corrupted.", file));
+        }
+
+        let target = install_dir.join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(&temp_path, &target)?;
+    }
+
+    Ok(())
+}
+
+// app ids and platforms are told apart the same way the manifest does, case aside
+fn is_target(patch: &PatchInfo, app: &str) -> bool {
+    manifest::normalize_app_id(&patch.app) == manifest::normalize_app_id(app)
+        && patch.platform.eq_ignore_ascii_case(PLATFORM)
+}
+
+// a server mixing up its games would otherwise patch another game's files over this one
+fn check_targets(patches: &[PatchInfo], app: &str) -> Result<()> {
+    for patch in patches {
+        if !is_target(patch, app) {
+            log::error!(
+                "patch {} is for {} on {}, but {} on {} was asked for",
+                patch.id,
+                patch.app,
+                patch.platform,
+                app,
+                PLATFORM
+            );
+            return Err(eyre!(
+                "The update server sent a patch for a different game or platform."
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// a version we can't make sense of shouldn't lock anyone out
+fn gate_patch_list(patch_list: PatchListResponse) -> Result<Vec<PatchInfo>> {
+    let (patches, min_client_version) = match patch_list {
+        PatchListResponse::Bare(patches) => (patches, None),
+        PatchListResponse::Gated {
+            patches,
+            min_client_version,
+        } => (patches, min_client_version),
+    };
+
+    if let Some(min_client_version) = min_client_version {
+        match self_update::parse_tag(&min_client_version) {
+            Some(required) if Version::parse(CURRENT_VERSION)? < required => {
+                log::error!(
+                    "update server requires launcher v{}, this is v{}",
+                    required,
+                    CURRENT_VERSION
+                );
+                return Err(ClientTooOld { required }.into());
+            }
+            Some(_) => {}
+            None => log::warn!(
+                "ignoring unrecognized minimum launcher version {}",
+                min_client_version
+            ),
+        }
+    }
+
+    Ok(patches)
+}
+
+// how often a paused download looks whether it can carry on
+const PAUSE_POLL_MILLIS: u64 = 200;
+
+// io::copy is stuck with an 8KiB buffer, which means a lot of tiny reads on fast, high latency
+// links, and can't be stopped until the whole file is in, so cancelling is checked between reads
+fn copy_buffered<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+    cancel: &CancelToken,
+) -> Result<u64> {
+    let mut buf = vec![0u8; buffer_size];
+    let mut copied = 0;
+    let started = time::Instant::now();
+    loop {
+        // a paused download keeps its connection and just stops reading
+        while cancel.is_paused() && !cancel.is_cancelled() {
+            thread::sleep(time::Duration::from_millis(PAUSE_POLL_MILLIS));
+        }
+        if cancel.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        let read_len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(read_len) => read_len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        writer.write_all(&buf[..read_len])?;
+        copied += read_len as u64;
+
+        // a throttled download waits until it's no further along than the limit allows
+        let rate_limit = cancel.rate_limit();
+        if rate_limit > 0 {
+            let allowed = time::Duration::from_secs_f64(copied as f64 / rate_limit as f64);
+            if let Some(ahead) = allowed.checked_sub(started.elapsed()) {
+                thread::sleep(ahead);
+            }
+        }
+    }
+}
+
+// certificates are only valid between two dates, a clock that's far enough off makes every
+// one of them look expired or not yet valid, as rustls and Windows' own TLS put it
+const CLOCK_SKEW_NEEDLES: [&str; 5] = [
+    "certexpired",
+    "certnotvalidyet",
+    "certificate has expired",
+    "not yet valid",
+    "validity period",
+];
+
+fn is_clock_skew(e: &reqwest::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(e);
+    while let Some(error) = source {
+        let message = error.to_string().to_lowercase();
+        if CLOCK_SKEW_NEEDLES
+            .iter()
+            .any(|needle| message.contains(needle))
+        {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+// a certificate rejected for its dates is reported as the clock problem it most likely is,
+// every other failed request is passed on as is
+fn request_error(e: reqwest::Error) -> eyre::Report {
+    if e.is_redirect() {
+        return eyre!("The request was redirected to a server that is not allowed.");
+    }
+    if !is_clock_skew(&e) {
+        return e.into();
+    }
+
+    log::error!(
+        "certificate rejected for its validity dates, the system clock reads {}: {}",
+        Utc::now(),
+        e
+    );
+    Error::network("The update server's certificate appears expired or not yet valid, which usually means this computer's date and time are wrong. Please correct them and try again.").into()
+}
+
+// a rate limited server says when to come back, in seconds or as a date, waits are capped so a
+// misconfigured server can't hold the launcher up for good
+pub fn retry_delay(retry_after: Option<&str>) -> time::Duration {
+    let secs = retry_after
+        .map(str::trim)
+        .and_then(|retry_after| {
+            retry_after.parse::<u64>().ok().or_else(|| {
+                DateTime::parse_from_rfc2822(retry_after)
+                    .ok()
+                    .map(|date| (date.with_timezone(&Utc) - Utc::now()).num_seconds().max(0) as u64)
+            })
+        })
+        .unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+    time::Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS))
+}
+
+// sends the request, waiting out 429 Too Many Requests for as long as the server asks to
+fn send_request(
+    request: RequestBuilder,
+    send_state: Option<&Sender<WorkerMsg>>,
+    cancel: Option<&CancelToken>,
+) -> Result<Response> {
+    let mut retries = 0;
+    loop {
+        let attempt = request
+            .try_clone()
+            .ok_or_else(|| eyre!("An error has occured."))?;
+        let resp = send_cancellable(attempt, cancel)?;
+        if resp.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(resp);
+        }
+        if retries == RATE_LIMIT_RETRIES {
+            log::error!("{} is still rate limited, giving up", resp.url());
+            return Err(eyre!(
+                "The update server is too busy right now, please try again later."
+            ));
+        }
+        retries += 1;
+
+        let delay = retry_delay(
+            resp.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|retry_after| retry_after.to_str().ok()),
+        );
+        log::warn!(
+            "{} is rate limited, retrying in {}s",
+            resp.url(),
+            delay.as_secs()
+        );
+        if let Some(send_state) = send_state {
+            send_state
+                .send(WorkerMsg::Status(format!(
+                    "Server busy, retrying in {}s...",
+                    delay.as_secs()
+                )))
+                .unwrap();
+        }
+
+        wait(delay, cancel)?;
+    }
+}
+
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.trim().to_lowercase().starts_with("text/html"))
+        .unwrap_or(false)
+}
+
+// misconfigured CDNs answer with an error page and a 200 instead of the file, which would only
+// show up as a confusing checksum mismatch later on. patches and signatures are never HTML, so
+// a page is asked for again like any other failed delivery
+fn send_download(
+    request: RequestBuilder,
+    send_state: Option<&Sender<WorkerMsg>>,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<Response> {
+    let mut retries = 0;
+    loop {
+        let attempt = request
+            .try_clone()
+            .ok_or_else(|| eyre!("An error has occured."))?;
+        let resp = send_request(attempt, send_state, Some(cancel))?;
+        if !is_html(resp.headers()) {
+            return Ok(resp);
+        }
+
+        let url = resp.url().to_string();
+        let mut page = Vec::new();
+        let _ = resp
+            .take(MAX_LOGGED_BODY_CHARS as u64)
+            .read_to_end(&mut page);
+        log::error!(
+            "{} sent an HTML page instead of the file: {}",
+            url,
+            String::from_utf8_lossy(&page)
+        );
+
+        let max_retries = settings.download_retries();
+        if retries >= max_retries {
+            return Err(Error::Delivery { url }.into());
+        }
+        retries += 1;
+        log::warn!("downloading {} again ({}/{})", url, retries, max_retries);
+        back_off(retries, settings, cancel)?;
+    }
+}
+
+// a blocking request can't be interrupted, so it's left to finish on a thread of its own while
+// this one keeps an eye on the cancel token. one that's given up on goes on until it's answered
+// or times out, with nobody left to hear back
+fn send_cancellable(request: RequestBuilder, cancel: Option<&CancelToken>) -> Result<Response> {
+    let cancel = match cancel {
+        Some(cancel) => cancel,
+        None => return request.send().map_err(request_error),
+    };
+
+    let (send_resp, recv_resp) = bounded(1);
+    thread::spawn(move || {
+        let _ = send_resp.send(request.send());
+    });
+    loop {
+        match recv_resp.recv_timeout(time::Duration::from_millis(PAUSE_POLL_MILLIS)) {
+            Ok(resp) => return resp.map_err(request_error),
+            Err(_) if cancel.is_cancelled() => {
+                log::info!("abandoning a request in flight, the update was cancelled");
+                return Err(Cancelled.into());
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err(eyre!("An error has occured.")),
+        }
+    }
+}
+
+// sleeps in steps short enough for cancelling to get through
+fn wait(delay: time::Duration, cancel: Option<&CancelToken>) -> Result<()> {
+    let waiting_since = time::Instant::now();
+    while waiting_since.elapsed() < delay {
+        if matches!(cancel, Some(cancel) if cancel.is_cancelled()) {
+            return Err(Cancelled.into());
+        }
+        thread::sleep(time::Duration::from_millis(PAUSE_POLL_MILLIS).min(delay));
+    }
+    Ok(())
+}
+
+// the wait before a retry, counting from 1, doubling with every one after the first
+fn back_off(retry: usize, settings: &Settings, cancel: &CancelToken) -> Result<()> {
+    let backoff_millis = settings.retry_backoff().as_millis() as u64;
+    let delay_millis = backoff_millis.saturating_mul(1 << retry.saturating_sub(1).min(16));
+    if delay_millis > 0 {
+        log::info!("waiting {} ms before retrying", delay_millis);
+    }
+    wait(time::Duration::from_millis(delay_millis), Some(cancel))
+}
+
+// timeouts and refused connections, as opposed to the server answering with something wrong
+pub fn is_unreachable(e: &eyre::Report) -> bool {
+    matches!(
+        e.downcast_ref::<reqwest::Error>(),
+        Some(e) if e.is_timeout() || e.is_request()
+    )
+}
+
+fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.to_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        let allowed = allowed.trim().to_lowercase();
+        match allowed.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => host == allowed,
+        }
+    })
+}
+
+// every patch and signature has to come from a host that's allowed, the two are checked on
+// their own since they're often served from different places
+pub fn check_hosts(patch_list: &[PatchInfo], allowed_hosts: &[String]) -> Result<()> {
+    for patch in patch_list {
+        for (what, url) in [("patch", &patch.url), ("signature", &patch.sig)].iter() {
+            let host = reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string));
+            match host {
+                Some(host) if host_allowed(&host, allowed_hosts) => {}
+                _ => {
+                    log::error!(
+                        "rejecting the {} of patch {} from {}, its host is not one of {}",
+                        what,
+                        patch.id,
+                        url,
+                        allowed_hosts.join(", ")
+                    );
+                    return Err(eyre!(
+                        "The update server sent a patch from a location that isn't allowed."
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the patch list of a local folder, written like the server's
+pub const LOCAL_PATCH_LIST: &str = "patches.json";
+
+const LOCAL_PREFIX: &str = "file://";
+
+fn local_file(url: &str) -> Option<&Path> {
+    url.strip_prefix(LOCAL_PREFIX).map(Path::new)
+}
+
+// patches from a folder instead of the update server, so one machine can download them once
+// and share them with machines that can't, e.g. at a LAN party. the folder holds a patch list
+// like the server's and the files it names, which are taken from the end of their URLs
+pub fn local_patch_list(dir: &Path, version: u16) -> Result<Vec<PatchInfo>> {
+    let patch_list_path = dir.join(LOCAL_PATCH_LIST);
+    let patch_list_body = fs::read_to_string(&patch_list_path).map_err(|e| {
+        eyre!(
+            "The local patch list {} could not be read ({}).",
+            patch_list_path.display(),
+            e
+        )
+    })?;
+    let patch_list = serde_json::from_str::<PatchListResponse>(&patch_list_body).map_err(|e| {
+        log::error!("unexpected local patch list ({})", e);
+        eyre!("The local patch list is not a valid patch list.")
+    })?;
+
+    // the server only sends what's newer than the install, a folder has everything
+    let mut patches: Vec<PatchInfo> = gate_patch_list(patch_list)?
+        .into_iter()
+        .filter(|patch| is_target(patch, APP))
+        .filter(|patch| patch.id > version as u64)
+        .collect();
+    patches.sort_by_key(|patch| patch.id);
+
+    for patch in patches.iter_mut() {
+        for (url, extension) in [(&mut patch.url, "pwr"), (&mut patch.sig, "pwr.sig")].iter_mut() {
+            let file_name = match url.rsplit('/').next() {
+                Some(file_name) if !file_name.is_empty() => file_name.to_string(),
+                _ => format!("{}.{}", patch.id, extension),
+            };
+            let local_path = dir.join(&file_name);
+            if !local_path.is_file() {
+                return Err(eyre!(
+                    "{} is missing from the local patch folder.",
+                    file_name
+                ));
+            }
+            **url = format!("{}{}", LOCAL_PREFIX, local_path.display());
+        }
+    }
+
+    log::info!("{} patches to apply from {}", patches.len(), dir.display());
+    Ok(patches)
+}
+
+#[cfg(windows)]
+fn is_network_unreachable(e: &io::Error) -> bool {
+    // WSAENETUNREACH and WSAEHOSTUNREACH
+    matches!(e.raw_os_error(), Some(10051) | Some(10065))
+}
+
+#[cfg(not(windows))]
+fn is_network_unreachable(e: &io::Error) -> bool {
+    // ENETUNREACH and EHOSTUNREACH
+    matches!(e.raw_os_error(), Some(101) | Some(113))
+}
+
+// whether this machine has a network to reach the update server, or the proxy in front of it,
+// over at all. without one looking up the name already fails, so an offline machine finds out
+// right away instead of after every request has timed out. a server that refuses or doesn't
+// answer is down, not offline, the requests themselves report that. anything that isn't a
+// plain address to connect to counts as online too
+pub fn is_online(url: &str) -> bool {
+    let url = match reqwest::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return true,
+    };
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.to_string(), port),
+        _ => return true,
+    };
+
+    // name lookups can't be given a timeout, so one that hangs is left behind on its own thread
+    let (send_addrs, recv_addrs) = bounded(1);
+    thread::spawn({
+        let host = host.clone();
+        move || {
+            let addrs = (host.as_str(), port)
+                .to_socket_addrs()
+                .map(|addrs| addrs.collect::<Vec<_>>());
+            let _ = send_addrs.send(addrs);
+        }
+    });
+    let timeout = time::Duration::from_millis(CONNECTIVITY_PROBE_TIMEOUT_MILLIS);
+    let addrs =
+        match recv_addrs.recv_timeout(time::Duration::from_millis(NAME_LOOKUP_TIMEOUT_MILLIS)) {
+            Ok(Ok(addrs)) => addrs,
+            Ok(Err(e)) => {
+                log::warn!("could not look up {}: {}", host, e);
+                return false;
+            }
+            Err(_) => {
+                log::warn!("looking up {} timed out", host);
+                return false;
+            }
+        };
+
+    let mut unreachable = !addrs.is_empty();
+    for addr in addrs.iter() {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(_) => return true,
+            Err(e) if is_network_unreachable(&e) => {}
+            Err(e) => {
+                log::warn!("could not connect to {} on port {}: {}", host, port, e);
+                unreachable = false;
+            }
+        }
+    }
+    if unreachable {
+        log::warn!("no network to reach {} over", host);
+    }
+    !unreachable
+}
+
+// a quick ping that only cares whether the server is up at all
+pub fn check_server_health(settings: &Settings, cancel: Option<&CancelToken>) -> Result<()> {
+    let health_req = http_client(settings)
+        .get(&format!(
+            "{}{}",
+            settings.server(),
+            settings.health_check_path
+        ))
+        .timeout(time::Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS));
+    let health_resp = send_cancellable(health_req, cancel).map_err(|e| {
+        // a wrong clock and cancelling come with explanations of their own
+        if e.downcast_ref::<reqwest::Error>().is_none() {
+            return e;
+        }
+        log::error!("health check failed: {}", e);
+        Error::network("The update server is down or could not be reached.").into()
+    })?;
+
+    let status = health_resp.status();
+    if status.is_server_error() {
+        let headers = health_resp.headers().clone();
+        let body = health_resp.text().unwrap_or_default();
+        if let Some(maintenance) = maintenance(status, &headers, &body) {
+            return Err(maintenance.into());
+        }
+        log::error!("health check returned {}", status);
+        return Err(Error::network(format!("The update server is down ({}).", status)).into());
+    }
+
+    Ok(())
+}
+
+// the server may list how big a patch is, otherwise ask for the headers of the download itself
+fn patch_size(patch: &PatchInfo, settings: &Settings) -> Option<u64> {
+    if patch.size.is_some() {
+        return patch.size;
+    }
+    if let Some(local_path) = local_file(&patch.url) {
+        return fs::metadata(local_path).ok().map(|metadata| metadata.len());
+    }
+
+    // content_length() reports the empty body of a HEAD response, not the header
+    let head_resp = http_client(settings)
+        .head(&patch.url)
+        .timeout(time::Duration::from_secs(CONTACT_SERVER_TIMEOUT_SECS))
+        .send()
+        .ok()?;
+    if !head_resp.status().is_success() {
+        return None;
+    }
+    head_resp
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+// how a download shows up on the progress bar
+enum DownloadProgress<'a> {
+    Hidden,
+    // counted as a task of its own
+    Task(&'a mut Task),
+    // only keeps the watchdog happy, for downloads whose task was already counted
+    Heartbeat(&'a TaskProgress),
+}
+
+// returns whether the download matched the digest the server sent along with it, if it did
+fn download_to(
+    url: &str,
+    path: &Path,
+    progress: DownloadProgress,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<Option<bool>> {
+    let buffer_size = settings.download_buffer_size();
+    // portable installs keep temp files in a directory of their own that may not exist yet
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out_file = fs::File::create(path)?;
+    let mut pauses_seen = cancel.pauses();
+    let send_state = match &progress {
+        DownloadProgress::Task(task) => Some(task.progress.send_state.clone()),
+        DownloadProgress::Heartbeat(progress) => Some(progress.send_state.clone()),
+        DownloadProgress::Hidden => None,
+    };
+    let mut heartbeat;
+    let (task, counted) = match progress {
+        DownloadProgress::Task(task) => (Some(task), true),
+        DownloadProgress::Heartbeat(progress) => {
+            heartbeat = progress.heartbeat();
+            (Some(&mut heartbeat), false)
+        }
+        DownloadProgress::Hidden => (None, false),
+    };
+
+    // patches from a local folder take the same way through, they're only copied instead
+    if let Some(local_path) = local_file(url) {
+        log::info!("copying {}", local_path.display());
+        let local_file = fs::File::open(local_path)?;
+        let mut copy_reader = ProgressReader {
+            expected_len: Some(local_file.metadata()?.len()).filter(|_| counted),
+            digest: None,
+            inner: local_file,
+            task,
+            read_len: 0,
+            metered: false,
+        };
+        copy_buffered(&mut copy_reader, &mut out_file, buffer_size, cancel)?;
+        if let (true, Some(task)) = (counted, copy_reader.task) {
+            task.finish();
+        }
+        return Ok(None);
+    }
+
+    let client = http_client(settings);
+    let download_resp = send_download(client.get(url), send_state.as_ref(), settings, cancel)?;
+    // HTTP/2 here means the download was multiplexed onto an already open connection
+    log::info!("downloading {} over {:?}", url, download_resp.version());
+
+    let mut download_reader = ProgressReader {
+        expected_len: download_resp.content_length().filter(|_| counted),
+        digest: TransportDigest::from_headers(download_resp.headers()),
+        inner: download_resp,
+        task,
+        read_len: 0,
+        metered: true,
+    };
+    let mut transport_check = None;
+    let mut digest_retries = 0;
+
+    loop {
+        match copy_buffered(&mut download_reader, &mut out_file, buffer_size, cancel) {
+            Ok(_) => {
+                let digest = match download_reader.digest.take() {
+                    Some(digest) => digest,
+                    None => break,
+                };
+                let algorithm = digest.algorithm;
+                let matched = digest.matches();
+                transport_check = Some(matched);
+                if matched {
+                    break;
+                }
+
+                // damaged on the way, cheaper to notice here than in the checks that follow
+                let max_retries = settings.download_retries();
+                if digest_retries >= max_retries {
+                    log::error!(
+                        "{} still does not match its {} digest, leaving it to the patch list checksum",
+                        url,
+                        algorithm
+                    );
+                    break;
+                }
+                digest_retries += 1;
+                log::warn!(
+                    "{} does not match its {} digest, downloading again ({}/{})",
+                    url,
+                    algorithm,
+                    digest_retries,
+                    max_retries
+                );
+                back_off(digest_retries, settings, cancel)?;
+
+                let retried_resp =
+                    send_download(client.get(url), send_state.as_ref(), settings, cancel)?;
+                out_file.set_len(0)?;
+                out_file.seek(io::SeekFrom::Start(0))?;
+                download_reader.read_len = 0;
+                download_reader.digest = TransportDigest::from_headers(retried_resp.headers());
+                download_reader.inner = retried_resp;
+            }
+            // servers drop connections that sit idle for too long, after a pause that's worth
+            // picking up where it broke off instead of failing the whole update
+            Err(e) if cancel.pauses() > pauses_seen && !cancel.is_cancelled() => {
+                pauses_seen = cancel.pauses();
+                let offset = download_reader.read_len;
+                // a digest covers the whole file, not the part a range request brings back
+                download_reader.digest = None;
+                log::warn!(
+                    "download of {} broke off after a pause at {} bytes, resuming: {}",
+                    url,
+                    offset,
+                    e
+                );
+
+                let resumed_resp = send_download(
+                    client
+                        .get(url)
+                        .header(reqwest::header::RANGE, format!("bytes={}-", offset)),
+                    send_state.as_ref(),
+                    settings,
+                    cancel,
+                )?;
+                if !resumed_resp.status().is_success() {
+                    return Err(eyre!(
+                        "The download could not be resumed ({}).",
+                        resumed_resp.status()
+                    ));
+                }
+                let remaining_len = resumed_resp.content_length();
+                if resumed_resp.status() == StatusCode::PARTIAL_CONTENT {
+                    download_reader.expected_len =
+                        remaining_len.map(|len| offset + len).filter(|_| counted);
+                } else {
+                    // no range support, the whole file comes again
+                    log::warn!("{} does not support resuming, starting over", url);
+                    out_file.set_len(0)?;
+                    out_file.seek(io::SeekFrom::Start(0))?;
+                    download_reader.read_len = 0;
+                    download_reader.expected_len = remaining_len.filter(|_| counted);
+                }
+                download_reader.inner = resumed_resp;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if counted {
+        if let Some(task) = download_reader.task {
+            task.finish();
+        }
+    }
+
+    Ok(transport_check)
+}
+
+// how much of a file is hashed between progress reports
+const HASH_CHUNK_BYTES: usize = 1024 * 1024;
+
+// hashes a file a chunk at a time, so large patches neither sit in memory whole nor leave the
+// bar standing still while they're checked
+fn file_crc32c(path: &Path, mut task: Option<&mut Task>) -> io::Result<u32> {
+    let mut file = fs::File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut buf = vec![0u8; HASH_CHUNK_BYTES];
+    let mut crc = 0;
+    let mut hashed_len = 0;
+
+    loop {
+        let read_len = match file.read(&mut buf) {
+            Ok(0) => return Ok(crc),
+            Ok(read_len) => read_len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        crc = crc32c::crc32c_append(crc, &buf[..read_len]);
+        hashed_len += read_len as u64;
+
+        if let Some(task) = task.as_deref_mut() {
+            task.set_done(hashed_len.min(file_len) * TASK_UNITS / file_len.max(1));
+        }
+    }
+}
+
+// a mismatch is usually a download that got corrupted on the way, so fetch the file again a
+// few times before giving up, a checksum that comes back the same every time points at the
+// server's hash instead
+fn check_crc32c(
+    url: &str,
+    path: &Path,
+    expected: u32,
+    what: &str,
+    task: &mut Task,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let progress = task.progress.clone();
+    let mut actual = file_crc32c(path, Some(task))?;
+    let mut stable = true;
+    let mut retries = 0;
+    let max_retries = settings.download_retries();
+
+    while actual.ne(&expected) {
+        if retries >= max_retries {
+            if stable {
+                log::error!(
+                    "{} checksum was {} in all {} downloads, the server's {} is likely wrong",
+                    what,
+                    actual,
+                    retries + 1,
+                    expected
+                );
+            } else {
+                log::error!(
+                    "{} checksum still mismatched after {} downloads, they keep arriving corrupted",
+                    what,
+                    retries + 1
+                );
+            }
+            return Err(Error::Checksum {
+                what: what.to_string(),
+            }
+            .into());
+        }
+
+        retries += 1;
+        log::warn!(
+            "{} checksum mismatch, downloaded: {}, server: {}, downloading again ({}/{})",
+            what,
+            actual,
+            expected,
+            retries,
+            max_retries
+        );
+        back_off(retries, settings, cancel)?;
+        download_to(
+            url,
+            path,
+            DownloadProgress::Heartbeat(&progress),
+            settings,
+            cancel,
+        )?;
+
+        // the bar already counted this file once, only let on that something is happening
+        let retried = file_crc32c(path, Some(&mut progress.heartbeat()))?;
+        stable = stable && retried == actual;
+        actual = retried;
+    }
+
+    if retries > 0 {
+        log::info!("{} checksum matched after {} retries", what, retries);
+    }
+    Ok(())
+}
+
+// a file that arrived as the server sent it but doesn't match the patch list was likely
+// packaged wrong, one that matches the patch list but not its digest was sent with a wrong one
+fn log_transport_check(what: &str, transport_check: Option<bool>, matched: bool) {
+    match (transport_check, matched) {
+        (Some(true), false) => log::error!(
+            "{} arrived as the server sent it but does not match the patch list, it was likely packaged wrong",
+            what
+        ),
+        (Some(false), true) => log::warn!(
+            "{} matches the patch list but not the digest it was delivered with, the server or CDN sends a wrong digest",
+            what
+        ),
+        _ => {}
+    }
+}
+
+// downloads a patch and its signature into the temp directory
+pub fn download_patch(
+    patch: &PatchInfo,
+    weights: TaskWeights,
+    progress: &TaskProgress,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<FetchedPatch> {
+    // download patch file
+    let mut task = progress.notify("Downloading File", weights.download);
+
+    let (patch_path, sig_path) = temp_patch_paths(patch);
+    let patch_file = TempFile { path: patch_path };
+    let patch_transport_check = download_to(
+        &patch.url,
+        &patch_file.path,
+        DownloadProgress::Task(&mut task),
+        settings,
+        cancel,
+    )?;
+
+    // download signature file
+    let mut task = progress.notify("Downloading File", weights.signature);
+
+    let sig_file = TempFile { path: sig_path };
+    let sig_transport_check = download_to(
+        &patch.sig,
+        &sig_file.path,
+        DownloadProgress::Task(&mut task),
+        settings,
+        cancel,
+    )?;
+
+    Ok(FetchedPatch {
+        patch_file,
+        sig_file,
+        sig_format: patch.sig_format,
+        patch_transport_check,
+        sig_transport_check,
+    })
+}
+
+// compares a downloaded patch and its signature against the patch list, either one is
+// downloaded again on a mismatch
+pub fn verify(
+    patch: &PatchInfo,
+    fetched: &FetchedPatch,
+    weights: TaskWeights,
+    progress: &TaskProgress,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<()> {
+    // comparing file checksum
+    let mut task = progress.notify(
+        "Comparing File Hashes",
+        TaskWeights::checksum(weights.download),
+    );
+
+    let what = format!("patch {}", patch.id);
+    let checked = check_crc32c(
+        &patch.url,
+        &fetched.patch_file.path,
+        patch.hash,
+        &what,
+        &mut task,
+        settings,
+        cancel,
+    );
+    log_transport_check(&what, fetched.patch_transport_check, checked.is_ok());
+    if let Err(e) = checked {
+        fetched.patch_file.preserve();
+        return Err(e);
+    }
+    task.finish();
+
+    // comparing file checksum
+    let mut task = progress.notify(
+        "Comparing Signature Hashes",
+        TaskWeights::checksum(weights.signature),
+    );
+
+    let what = format!("signature {}", patch.id);
+    let checked = check_crc32c(
+        &patch.sig,
+        &fetched.sig_file.path,
+        patch.sig_hash,
+        &what,
+        &mut task,
+        settings,
+        cancel,
+    );
+    log_transport_check(&what, fetched.sig_transport_check, checked.is_ok());
+    if let Err(e) = checked {
+        fetched.sig_file.preserve();
+        return Err(e);
+    }
+    task.finish();
+
+    if let Err(e) = check_minisign(patch, fetched, settings) {
+        fetched.patch_file.preserve();
+        fetched.sig_file.preserve();
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+// checks that a file was signed with the key a public key, as it's written on the second line
+// of a minisign.pub, belongs to
+pub fn verify_minisign(path: &Path, sig_path: &Path, public_key: &str) -> Result<()> {
+    let public_key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| eyre!("The minisign public key is not valid ({}).", e))?;
+    let signature = minisign_verify::Signature::decode(&fs::read_to_string(sig_path)?)?;
+    let mut verifier = public_key.verify_stream(&signature)?;
+
+    let mut file = fs::File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        verifier.update(&buffer[..read]);
+    }
+
+    Ok(verifier.finalize()?)
+}
+
+// the checksums in the patch list only catch damage on the way, a minisign signature also
+// proves who made the patch
+fn check_minisign(patch: &PatchInfo, fetched: &FetchedPatch, settings: &Settings) -> Result<()> {
+    if patch.sig_format != SigFormat::Minisign {
+        return Ok(());
+    }
+
+    let public_key = settings.minisign_public_key.as_deref().ok_or_else(|| {
+        eyre!(
+            "Patch {} is signed with minisign, but there is no public key to check it with.",
+            patch.id
+        )
+    })?;
+    verify_minisign(&fetched.patch_file.path, &fetched.sig_file.path, public_key).map_err(|e| {
+        log::error!(
+            "minisign signature of patch {} did not verify: {}",
+            patch.id,
+            e
+        );
+        Error::Untrusted { patch: patch.id }.into()
+    })
+}
+
+// patches downloaded during the download window wait here to be applied, the temp directory
+// is cleaned out on every start so they can't go there
+pub fn downloads_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("downloads")
+}
+
+// patches fetched in the background for another game, kept apart since patch ids only mean
+// something within a game. whichever launcher updates that game moves them in with its own.
+// outside of the downloads directory, which goes as a whole once this game is up to date
+fn prefetch_dir(data_dir: &Path, app: &str) -> PathBuf {
+    data_dir
+        .join("prefetch")
+        .join(manifest::normalize_app_id(app))
+}
+
+fn adopt_prefetched(data_dir: &Path) {
+    let prefetch_dir = prefetch_dir(data_dir, APP);
+    let prefetched = match fs::read_dir(&prefetch_dir) {
+        Ok(dir_entries) => dir_entries.flatten().collect::<Vec<_>>(),
+        Err(_) => return,
+    };
+    if let Err(e) = fs::create_dir_all(downloads_dir(data_dir)) {
+        log::warn!(
+            "could not create {}: {}",
+            downloads_dir(data_dir).display(),
+            e
+        );
+        return;
+    }
+
+    // the signature is moved in last, like with any other download
+    let (sigs, patches): (Vec<_>, Vec<_>) = prefetched
+        .into_iter()
+        .partition(|dir_entry| dir_entry.path().extension() == Some("sig".as_ref()));
+    for dir_entry in patches.into_iter().chain(sigs) {
+        let adopted = downloads_dir(data_dir).join(dir_entry.file_name());
+        if let Err(e) = move_file(&dir_entry.path(), &adopted) {
+            log::warn!("could not use {}: {}", dir_entry.path().display(), e);
+        }
+    }
+    let _ = fs::remove_dir(&prefetch_dir);
+}
+
+fn downloaded_paths(downloads_dir: &Path, patch: &PatchInfo) -> (PathBuf, PathBuf) {
+    (
+        downloads_dir.join(format!("{}.pwr", patch.id)),
+        downloads_dir.join(format!("{}.pwr.sig", patch.id)),
+    )
+}
+
+// the signature is moved in last, so a patch only counts once both files made it
+pub fn is_downloaded(downloads_dir: &Path, patch: &PatchInfo) -> bool {
+    let (patch_path, sig_path) = downloaded_paths(downloads_dir, patch);
+    patch_path.is_file() && sig_path.is_file()
+}
+
+// the data and temp directories can be on different drives, where renaming doesn't work
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to).or_else(|_| {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    })
+}
+
+// downloads and verifies patches without applying them, applying picks them up later on
+pub fn download_ahead(
+    patch_list: &[PatchInfo],
+    data_dir: &Path,
+    settings: &Settings,
+    send_state: &Sender<WorkerMsg>,
+    cancel: &CancelToken,
+) -> Result<()> {
+    adopt_prefetched(data_dir);
+    download_ahead_into(
+        patch_list,
+        &downloads_dir(data_dir),
+        settings,
+        send_state,
+        cancel,
+    )
+}
+
+fn download_ahead_into(
+    patch_list: &[PatchInfo],
+    downloads_dir: &Path,
+    settings: &Settings,
+    send_state: &Sender<WorkerMsg>,
+    cancel: &CancelToken,
+) -> Result<()> {
+    fs::create_dir_all(downloads_dir)?;
+
+    let pending: Vec<&PatchInfo> = patch_list
+        .iter()
+        .filter(|patch| !is_downloaded(downloads_dir, patch))
+        .collect();
+    let patch_sizes: Vec<Option<u64>> = pending
+        .iter()
+        .map(|patch| patch_size(patch, settings))
+        .collect();
+    let download_size: u64 = patch_sizes.iter().flatten().sum();
+    if download_size > 0 {
+        log::info!(
+            "{} patches to download ahead, {} bytes",
+            pending.len(),
+            download_size
+        );
+        send_state
+            .send(WorkerMsg::DownloadSize(download_size))
+            .unwrap();
+    }
+
+    let weights = task_weights(&patch_sizes);
+    let progress = TaskProgress::without_applying(send_state.clone(), &weights);
+    for (patch, weights) in pending.into_iter().zip(weights) {
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+
+        let fetched = download_patch(patch, weights, &progress, settings, cancel)?;
+        verify(patch, &fetched, weights, &progress, settings, cancel)?;
+
+        let (patch_path, sig_path) = downloaded_paths(downloads_dir, patch);
+        move_file(&fetched.patch_file.path, &patch_path)?;
+        move_file(&fetched.sig_file.path, &sig_path)?;
+        log::info!("downloaded patch {} ahead of time", patch.id);
+    }
+
+    Ok(())
+}
+
+// downloads, without applying, what another installed game needs to be up to date, so it's
+// ready the next time that game is launched. meant to run in the background at a throttled
+// rate, the cancel token's throttle and pausing take care of that
+pub fn prefetch(
+    app: &str,
+    entry: &AppEntry,
+    data_dir: &Path,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let mut patch_list = fetch_app_patch_list(settings, app, entry.patch, None, Some(cancel))?;
+    check_hosts(&patch_list, &settings.allowed_hosts())?;
+    // patches past a game's lock would never be applied, they'd only use up the bandwidth
+    lock_version(
+        &mut patch_list,
+        entry.patch,
+        settings.game(app).version_lock,
+    );
+    if patch_list.is_empty() {
+        log::info!("{} is up to date, nothing to prefetch", app);
+        return Ok(());
+    }
+
+    // whatever an earlier prefetch of this game left behind, other games' files are left alone
+    let _ = fs::remove_dir_all(app_temp_dir(app));
+
+    // nobody is watching the progress, it's only logged
+    let (send_state, recv_state) = unbounded();
+    thread::spawn(move || recv_state.into_iter().for_each(drop));
+    log::info!("prefetching {} patches for {}", patch_list.len(), app);
+    download_ahead_into(
+        &patch_list,
+        &prefetch_dir(data_dir, app),
+        settings,
+        &send_state,
+        cancel,
+    )
+}
+
+// picks up a patch downloaded ahead of time, it goes where a fresh download would so it's
+// verified and cleaned up the same way
+fn take_downloaded(
+    downloads_dir: &Path,
+    patch: &PatchInfo,
+    weights: TaskWeights,
+    progress: &TaskProgress,
+) -> Option<FetchedPatch> {
+    if !is_downloaded(downloads_dir, patch) {
+        return None;
+    }
+
+    let (patch_path, sig_path) = temp_patch_paths(patch);
+    let fetched = FetchedPatch {
+        patch_file: TempFile { path: patch_path },
+        sig_file: TempFile { path: sig_path },
+        sig_format: patch.sig_format,
+        // nothing is known about how they were delivered, that was in another run
+        patch_transport_check: None,
+        sig_transport_check: None,
+    };
+    let (downloaded_patch, downloaded_sig) = downloaded_paths(downloads_dir, patch);
+    if let Err(e) = move_file(&downloaded_patch, &fetched.patch_file.path)
+        .and_then(|_| move_file(&downloaded_sig, &fetched.sig_file.path))
+    {
+        log::warn!("could not use the download of patch {}: {}", patch.id, e);
+        return None;
+    }
+
+    log::info!("using patch {} downloaded ahead of time", patch.id);
+    progress
+        .notify("Using Downloaded File", weights.download)
+        .finish();
+    progress
+        .notify("Using Downloaded File", weights.signature)
+        .finish();
+    Some(fetched)
+}
+
+// the files of a patch, unchecked, whether they were downloaded ahead of time or only now
+fn obtain_patch(
+    patch: &PatchInfo,
+    downloads_dir: &Path,
+    weights: TaskWeights,
+    progress: &TaskProgress,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<FetchedPatch> {
+    match take_downloaded(downloads_dir, patch, weights, progress) {
+        Some(fetched) => Ok(fetched),
+        None => download_patch(patch, weights, progress, settings, cancel),
+    }
+}
+
+fn fetch_patch(
+    patch: &PatchInfo,
+    downloads_dir: &Path,
+    weights: TaskWeights,
+    progress: &TaskProgress,
+    settings: &Settings,
+    cancel: &CancelToken,
+) -> Result<FetchedPatch> {
+    let fetched = obtain_patch(patch, downloads_dir, weights, progress, settings, cancel)?;
+    verify(patch, &fetched, weights, progress, settings, cancel)?;
+    Ok(fetched)
+}
+
+// how often a running backup says how far it got
+const BACKUP_REPORT_INTERVAL: time::Duration = time::Duration::from_millis(250);
+
+// a crash or a killed launcher leaves the last patch's staging directory behind, butler has
+// to start from an empty one every time
+fn staging_dir() -> io::Result<PathBuf> {
+    let staging_dir = paths::temp_path("butler-workingdir");
+    let leftover = fs::read_dir(&staging_dir)
+        .map(|mut dir_entries| dir_entries.next().is_some())
+        .unwrap_or(false);
+    if leftover {
+        log::warn!("removing a leftover butler staging directory");
+        let _ = fs::remove_dir_all(&staging_dir);
+    }
+    fs::create_dir_all(&staging_dir)?;
+    Ok(staging_dir)
+}
+
+// runs butler over the install directory for one patch, on failure the errors butler
+// logged tell whether the patch itself was damaged
+pub fn apply(
+    fetched: &FetchedPatch,
+    install_dir: &Path,
+    task: &mut Task,
+    cancel: &CancelToken,
+) -> Result<(), butler::Failure> {
+    let staging_dir = match staging_dir() {
+        Ok(staging_dir) => staging_dir,
+        Err(e) => {
+            log::error!("failed to create butler's staging directory: {}", e);
+            return Err(butler::Failure::Unknown);
+        }
+    };
+    defer! {
+        if let Err(e) = fs::remove_dir_all(&staging_dir) {
+            log::warn!("failed to clean up butler's staging directory: {}", e);
+        }
+    }
+    // only a wharf signature says what the result has to look like
+    let signature =
+        Some(fetched.sig_file.path.as_path()).filter(|_| fetched.sig_format == SigFormat::Wharf);
+    // a butler that went missing or got blocked, e.g. quarantined by an antivirus, can't start
+    let mut butler_child = match butler::apply_command(
+        &fetched.patch_file.path,
+        install_dir,
+        signature,
+        &staging_dir,
+    )
+    .stdin(process::Stdio::null())
+    .stdout(process::Stdio::piped())
+    .stderr(process::Stdio::null())
+    .spawn()
+    {
+        Ok(butler_child) => butler_child,
+        Err(e) => {
+            log::error!("failed to start butler: {}", e);
+            return Err(butler::Failure::Unknown);
+        }
+    };
+
+    let mut butler_errors = Vec::new();
+
+    // forward butler's progress as it goes, big patches can take minutes to apply
+    let butler_stdout = match butler_child.stdout.take() {
+        Some(butler_stdout) => io::BufReader::new(butler_stdout),
+        None => {
+            log::error!("failed to read butler's output");
+            let _ = butler_child.kill();
+            return Err(butler::Failure::Unknown);
+        }
+    };
+    cancel.track(butler_child);
+    for line in butler_stdout.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        match butler::parse_event(&line) {
+            Some(butler::Event::Progress(alpha)) => {
+                task.set_done((alpha * TASK_UNITS as f64) as u64);
+            }
+            Some(butler::Event::Log(level, message)) => {
+                log::log!(level, "butler: {}", message);
+                if level == log::Level::Error {
+                    butler_errors.push(message);
+                }
+            }
+            None => {}
+        }
+    }
+
+    let butler_status = match cancel.untrack().map(|mut butler_child| butler_child.wait()) {
+        Some(Ok(butler_status)) => butler_status,
+        Some(Err(e)) => {
+            log::error!("failed to wait for butler: {}", e);
+            return Err(butler::Failure::Unknown);
+        }
+        None => {
+            log::error!("lost track of butler before it finished");
+            return Err(butler::Failure::Unknown);
+        }
+    };
+    if !butler_status.success() {
+        if cancel.is_cancelled() {
+            return Err(butler::Failure::Unknown);
+        }
+        return Err(butler::classify_failure(&butler_errors));
+    }
+
+    Ok(())
+}
+
+// an adopted install is only trusted once butler agrees it matches the claimed patch exactly
+pub fn verify_adopted_install(
+    entry: &AppEntry,
+    settings: &Settings,
+    local_patches: Option<&Path>,
+    cancel: &CancelToken,
+) -> Result<()> {
+    butler::ensure_supported()?;
+
+    let patch_list = match local_patches {
+        Some(local_patches) => local_patch_list(local_patches, 0)?,
+        None => {
+            let patch_list = fetch_patch_list(settings, 0, None, Some(cancel))?;
+            check_hosts(&patch_list, &settings.allowed_hosts())?;
+            patch_list
+        }
+    };
+    let patch = patch_list
+        .iter()
+        .find(|patch| patch.id == entry.patch as u64)
+        .ok_or_else(|| eyre!("Patch {} is not a known patch for this game.", entry.patch))?;
+    if patch.sig_format != SigFormat::Wharf {
+        return Err(eyre!(
+            "Patch {} has no signature an existing install can be checked against. Please choose an empty directory instead.",
+            entry.patch
+        ));
+    }
+
+    let sig_file = TempFile {
+        path: temp_patch_paths(patch).1,
+    };
+    download_to(
+        &patch.sig,
+        &sig_file.path,
+        DownloadProgress::Hidden,
+        settings,
+        cancel,
+    )?;
+
+    let sig_file_crc32c = file_crc32c(&sig_file.path, None)?;
+    if sig_file_crc32c.ne(&patch.sig_hash) {
+        return Err(Error::Checksum {
+            what: "signature".into(),
+        }
+        .into());
+    }
+
+    butler::verify(&sig_file.path, &entry.dir).map_err(|_| {
+        eyre!(
+            "The existing install does not match patch {}. Please choose an empty directory instead.",
+            entry.patch
+        )
+    })
+}
+
+// what patches the install directory, butler outside of tests, which have none to run
+#[derive(Clone, Copy)]
+pub struct Patcher {
+    pub ensure_supported: fn() -> Result<()>,
+    pub apply: fn(&FetchedPatch, &Path, &mut Task, &CancelToken) -> Result<(), butler::Failure>,
+}
+
+pub const BUTLER: Patcher = Patcher {
+    ensure_supported: butler::ensure_supported,
+    apply,
+};
+
+// a version locked game only gets the patches leading up to its lock
+pub fn lock_version(patch_list: &mut Vec<PatchInfo>, installed: u16, version_lock: Option<u16>) {
+    if let Some(version_lock) = version_lock {
+        if installed > version_lock {
+            log::warn!(
+                "installed patch {} is already past the lock at {}, downgrades are not supported",
+                installed,
+                version_lock
+            );
+        }
+        patch_list.retain(|patch| patch.id <= version_lock as u64);
+    }
+}
+
+// how far an update got, so closing the launcher halfway and opening it again carries on
+// counting from there instead of looking like a fresh start
+pub const PROGRESS_FILE: &str = "update.progress";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateProgress {
+    // the patch the update is headed for, a different target is a different update
+    pub target: u64,
+    pub total: usize,
+    pub applied: usize,
+    pub bytes_done: u64,
+    weight_done: u64,
+}
+
+pub fn load_progress(data_dir: &Path) -> Option<UpdateProgress> {
+    let progress = fs::read_to_string(data_dir.join(PROGRESS_FILE)).ok()?;
+    serde_json::from_str(&progress)
+        .map_err(|e| log::warn!("ignoring unreadable update progress: {}", e))
+        .ok()
+}
+
+fn save_progress(data_dir: &Path, progress: &UpdateProgress) {
+    // staged and renamed over like the manifest, a crash mid write mustn't leave half a file
+    let saved = serde_json::to_string(progress)
+        .map_err(eyre::Report::from)
+        .and_then(|progress| {
+            let staged_path = data_dir.join(format!("{}.tmp", PROGRESS_FILE));
+            let mut progress_file = fs::File::create(&staged_path)?;
+            progress_file.write_all(progress.as_bytes())?;
+            progress_file.sync_all()?;
+            drop(progress_file);
+            Ok(fs::rename(staged_path, data_dir.join(PROGRESS_FILE))?)
+        });
+    if let Err(e) = saved {
+        log::warn!("could not save the update progress: {}", e);
+    }
+}
+
+// only counts if it's the same update with exactly the patches it didn't get to left
+fn resumable_progress(data_dir: &Path, patch_list: &[PatchInfo]) -> Option<UpdateProgress> {
+    let target = patch_list.iter().map(|patch| patch.id).max()?;
+    load_progress(data_dir).filter(|progress| {
+        progress.target == target
+            && progress.applied > 0
+            && progress.total == progress.applied + patch_list.len()
+    })
+}
+
+// a patch listed twice would be applied twice, the first one is kept as long as the others are
+// the same patch. two different patches under one id can't be told apart, so that's an error
+pub fn dedupe_patches(patch_list: &[PatchInfo]) -> Result<Vec<PatchInfo>> {
+    let mut deduped: Vec<PatchInfo> = Vec::with_capacity(patch_list.len());
+    for patch in patch_list {
+        match deduped.iter().find(|kept| kept.id == patch.id) {
+            Some(kept) if kept == patch => {
+                log::warn!(
+                    "patch {} is listed more than once, ignoring the repeats",
+                    patch.id
+                );
+            }
+            Some(_) => {
+                log::error!(
+                    "patch {} is listed more than once with different contents",
+                    patch.id
+                );
+                return Err(eyre!(
+                    "The update server listed two different patches as patch {}.",
+                    patch.id
+                ));
+            }
+            None => deduped.push(patch.clone()),
+        }
+    }
+
+    Ok(deduped)
+}
+
+// a patch goes after the ones it requires, otherwise the list keeps its order. requirements
+// at or below the installed patch are already met, anything else has to be in the list
+pub fn order_by_requirements(patch_list: &[PatchInfo], installed: u16) -> Result<Vec<PatchInfo>> {
+    let listed: HashSet<u64> = patch_list.iter().map(|patch| patch.id).collect();
+    for patch in patch_list {
+        if let Some(missing) = patch
+            .requires
+            .iter()
+            .find(|required| **required > installed as u64 && !listed.contains(required))
+        {
+            log::error!(
+                "patch {} requires patch {}, which is neither installed nor in the patch list",
+                patch.id,
+                missing
+            );
+            return Err(eyre!(
+                "Patch {} needs patch {}, which is not installed and not part of this update.",
+                patch.id,
+                missing
+            ));
+        }
+    }
+
+    let mut pending = patch_list.to_vec();
+    let mut ordered: Vec<PatchInfo> = Vec::with_capacity(pending.len());
+    let mut placed: HashSet<u64> = HashSet::new();
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|patch| {
+            patch
+                .requires
+                .iter()
+                .all(|required| *required <= installed as u64 || placed.contains(required))
+        });
+        let ready = match ready {
+            Some(ready) => pending.remove(ready),
+            None => {
+                let ids: Vec<String> = pending.iter().map(|patch| patch.id.to_string()).collect();
+                log::error!("patches {} require each other", ids.join(", "));
+                return Err(eyre!(
+                    "The patches of this update require each other, so none of them can go first."
+                ));
+            }
+        };
+        if matches!(ordered.last(), Some(last) if last.id > ready.id) {
+            log::info!(
+                "applying patch {} early, it is required by a later patch",
+                ready.id
+            );
+        }
+        placed.insert(ready.id);
+        ordered.push(ready);
+    }
+
+    Ok(ordered)
+}
+
+// the highest patch everything up to has been applied, patches applied early for a later one
+// don't count until the ones before them are in too
+fn applied_through(patch_list: &[PatchInfo], applied: &HashSet<u64>) -> Option<u64> {
+    let mut ids: Vec<u64> = patch_list.iter().map(|patch| patch.id).collect();
+    ids.sort_unstable();
+    ids.into_iter().take_while(|id| applied.contains(id)).last()
+}
+
+// records where the game is at, other games in the manifest are left as they are
+pub fn save_manifest(
+    manifest: &mut InstallManifest,
+    entry: &AppEntry,
+    data_dir: &Path,
+) -> Result<()> {
+    manifest
+        .games
+        .insert(String::from("unnamed-sdvx-clone"), entry.clone());
+    manifest.save(data_dir).map_err(|e| {
+        log::error!("failed to save the install manifest: {}", e);
+        Error::Manifest {
+            reason: e.to_string(),
+        }
+        .into()
+    })
+}
+
+// downloads, verifies and applies every patch in the list to the game in the manifest, in
+// order, saving the manifest after each one, returns early without an error once cancelled
+pub fn update(
+    patch_list: &[PatchInfo],
+    manifest: &mut InstallManifest,
+    data_dir: &Path,
+    settings: &Settings,
+    patcher: Patcher,
+    send_state: &Sender<WorkerMsg>,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let mut entry = manifest
+        .games
+        .get("unnamed-sdvx-clone")
+        .cloned()
+        .ok_or_else(|| eyre!("The game is missing from the install manifest."))?;
+    let patch_list = &order_by_requirements(&dedupe_patches(patch_list)?, entry.patch)?[..];
+
+    // add up what's about to be downloaded so the user knows what they're in for
+    let patch_sizes: Vec<Option<u64>> = patch_list
+        .iter()
+        .map(|patch| patch_size(patch, settings))
+        .collect();
+    let download_size: u64 = patch_sizes.iter().flatten().sum();
+    if patch_sizes.iter().any(Option::is_none) {
+        log::warn!("the size of some patches is unknown, the download size is a lower bound");
+    }
+    if download_size > 0 {
+        log::info!(
+            "{} patches to apply, {} bytes to download",
+            patch_list.len(),
+            download_size
+        );
+        send_state
+            .send(WorkerMsg::DownloadSize(download_size))
+            .unwrap();
+    }
+
+    // make sure the bundled patching tool can handle the patches before touching anything
+    if !patch_list.is_empty() {
+        (patcher.ensure_supported)()?;
+    }
+
+    // keeps the game from starting while it's being patched, and the other way around
+    let _install_lock = if patch_list.is_empty() {
+        None
+    } else {
+        Some(install_lock::acquire(&entry.dir)?)
+    };
+
+    // clearing out what an earlier run left behind can take a moment on slow disks, say so
+    // rather than sitting there silently before the downloads start
+    if !patch_list.is_empty() {
+        send_state
+            .send(WorkerMsg::Status(
+                "Creating Temporary Directory...".to_string(),
+            ))
+            .unwrap();
+        let temp_dir = app_temp_dir(APP);
+        fs::create_dir_all(&temp_dir).map_err(|e| {
+            eyre!(
+                "The temporary directory {} could not be created ({}).",
+                temp_dir.display(),
+                e
+            )
+        })?;
+
+        send_state
+            .send(WorkerMsg::Status(
+                "Preparing Staging Directory...".to_string(),
+            ))
+            .unwrap();
+        staging_dir().map_err(|e| eyre!("The staging directory could not be prepared ({}).", e))?;
+    }
+
+    // keep what's installed now around in case the update turns out bad, a fresh install
+    // has nothing worth keeping
+    let backups_dir = backups::backups_dir(data_dir, "unnamed-sdvx-clone");
+    if !patch_list.is_empty() && settings.patch_backups > 0 && entry.patch > 0 {
+        send_state
+            .send(WorkerMsg::Status("Backing Up...".to_string()))
+            .unwrap();
+        // the watchdog only knows the backup is moving along from what it's told
+        let mut backed_up = 0;
+        let mut reported = time::Instant::now();
+        let mut copied = |bytes: u64| {
+            backed_up += bytes;
+            if reported.elapsed() >= BACKUP_REPORT_INTERVAL {
+                reported = time::Instant::now();
+                let _ = send_state.send(WorkerMsg::Status(format!(
+                    "Backing Up ({} MB)...",
+                    backed_up / (1024 * 1024)
+                )));
+            }
+        };
+        match backups::create(&entry.dir, &backups_dir, entry.patch, &mut copied) {
+            Ok(backup_dir) => log::info!(
+                "backed up patch {} to {}",
+                entry.patch,
+                backup_dir.display()
+            ),
+            Err(e) => log::warn!("could not back up patch {}: {}", entry.patch, e),
+        }
+    }
+    backups::prune(&backups_dir, settings.patch_backups);
+
+    // whatever was prefetched in the background while another game was played counts too
+    adopt_prefetched(data_dir);
+
+    // room for the downloads is set aside before the first one starts, each patch hands its
+    // share back right before downloading. patches downloaded ahead of time need none
+    let downloads_dir = downloads_dir(data_dir);
+    let reserved_sizes: Vec<u64> = patch_list
+        .iter()
+        .zip(patch_sizes.iter())
+        .map(|(patch, size)| match size {
+            Some(size) if !is_downloaded(&downloads_dir, patch) => *size,
+            _ => 0,
+        })
+        .collect();
+    let reserved_size: u64 = reserved_sizes.iter().sum();
+    if reserved_size > 0 {
+        send_state
+            .send(WorkerMsg::Status("Checking Disk Space...".to_string()))
+            .unwrap();
+    }
+    let reservation = match patch_list.iter().map(|patch| patch.id).max() {
+        Some(target) if reserved_size > 0 => Some(Arc::new(SpaceReservation::reserve(
+            app_temp_dir(APP).join(format!("{}{}", disk_space::RESERVATION_PREFIX, target)),
+            reserved_size,
+        )?)),
+        _ => None,
+    };
+    // applying needs room where the game is as well, which can be another drive entirely.
+    // what a patch writes isn't known up front, its size stands in for it. on the same drive
+    // the downloads' reservation already counts it
+    disk_space::remove_leftover_reservations(&entry.dir);
+    let apply_size: u64 = patch_sizes.iter().flatten().sum();
+    let apply_reservation = match patch_list.iter().map(|patch| patch.id).max() {
+        Some(_) if disk_space::same_volume(&app_temp_dir(APP), &entry.dir) => None,
+        Some(target) if apply_size > 0 => Some(SpaceReservation::reserve(
+            entry
+                .dir
+                .join(format!("{}{}", disk_space::RESERVATION_PREFIX, target)),
+            apply_size,
+        )?),
+        _ => None,
+    };
+
+    // iterate through patch list, the bar moves with the bytes being worked through
+    let weights = task_weights(&patch_sizes);
+    let mut progress = TaskProgress::new(send_state.clone(), &weights);
+
+    let mut update_progress = match resumable_progress(data_dir, patch_list) {
+        Some(resumed) => {
+            log::info!(
+                "resuming the update to patch {}, {} of {} patches were applied before",
+                resumed.target,
+                resumed.applied,
+                resumed.total
+            );
+            send_state
+                .send(WorkerMsg::Status(format!(
+                    "Resuming Update ({}/{})...",
+                    resumed.applied, resumed.total
+                )))
+                .unwrap();
+            progress = progress.resumed(&resumed);
+            resumed
+        }
+        None => UpdateProgress {
+            target: patch_list.iter().map(|patch| patch.id).max().unwrap_or(0),
+            total: patch_list.len(),
+            applied: 0,
+            bytes_done: 0,
+            weight_done: 0,
+        },
+    };
+
+    let mut applied = HashSet::new();
+
+    // downloads run ahead of butler so the next patch is usually ready by the time
+    // the current one finishes applying, applying itself stays strictly in order
+    let mut fetched_patches: Box<dyn Iterator<Item = Result<FetchedPatch>>> = if settings.prefetch()
+        == 0
+    {
+        let patch_list = patch_list.to_vec();
+        let weights = weights.clone();
+        let progress = progress.clone();
+        let downloads_dir = downloads_dir.clone();
+        let reserved_sizes = reserved_sizes.clone();
+        let reservation = reservation.clone();
+        let settings = settings.clone();
+        let cancel = cancel.clone();
+        Box::new(patch_list.into_iter().zip(weights).zip(reserved_sizes).map(
+            move |((patch, weights), reserved_size)| {
+                if let Some(reservation) = reservation.as_ref() {
+                    reservation.release(reserved_size);
+                }
+                fetch_patch(
+                    &patch,
+                    &downloads_dir,
+                    weights,
+                    &progress,
+                    &settings,
+                    &cancel,
+                )
+            },
+        ))
+    } else {
+        // checksums are worked out on a pool while the next patch downloads, each patch
+        // comes with its own channel for the result so they're still applied in order
+        let checksum_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(settings.checksum_threads)
+            .thread_name(|index| format!("checksum-{}", index))
+            .build()?;
+
+        // the fetch thread holds one finished patch while blocked on send,
+        // so the channel only has to buffer the rest of the look-ahead
+        let (send_fetched, recv_fetched) = bounded(settings.prefetch() - 1);
+        let patch_list = patch_list.to_vec();
+        let weights = weights.clone();
+        let progress = progress.clone();
+        let downloads_dir = downloads_dir.clone();
+        let reserved_sizes = reserved_sizes.clone();
+        let reservation = reservation.clone();
+        let settings = Arc::new(settings.clone());
+        let cancel = cancel.clone();
+        thread::spawn(move || {
+            for ((patch, weights), reserved_size) in
+                patch_list.into_iter().zip(weights).zip(reserved_sizes)
+            {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                if let Some(reservation) = reservation.as_ref() {
+                    reservation.release(reserved_size);
+                }
+
+                let (send_verified, recv_verified) = bounded(1);
+                let obtained = obtain_patch(
+                    &patch,
+                    &downloads_dir,
+                    weights,
+                    &progress,
+                    &settings,
+                    &cancel,
+                );
+                let failed = obtained.is_err();
+                match obtained {
+                    Ok(fetched) => {
+                        let progress = progress.clone();
+                        let settings = settings.clone();
+                        let cancel = cancel.clone();
+                        checksum_pool.spawn(move || {
+                            let verified =
+                                verify(&patch, &fetched, weights, &progress, &settings, &cancel)
+                                    .map(|_| fetched);
+                            let _ = send_verified.send(verified);
+                        });
+                    }
+                    Err(e) => {
+                        let _ = send_verified.send(Err(e));
+                    }
+                }
+
+                // the receiving end goes away once applying fails, stop downloading
+                if send_fetched.send(recv_verified).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Box::new(recv_fetched.into_iter().map(|recv_verified| {
+            recv_verified
+                .recv()
+                .unwrap_or_else(|_| Err(eyre!("An error has occured.")))
+        }))
+    };
+
+    for ((patch, weights), patch_size) in patch_list.iter().zip(weights.iter()).zip(patch_sizes) {
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+
+        let mut fetched = match fetched_patches.next() {
+            Some(Ok(fetched)) => fetched,
+            // a download stopped by cancelling isn't an error, the partial file is already gone
+            Some(Err(_)) if cancel.is_cancelled() => return Ok(()),
+            Some(Err(e)) => return Err(e),
+            None => return Err(eyre!("An error has occured.")),
+        };
+
+        // apply patch to directory
+        if let (Some(apply_reservation), Some(size)) = (apply_reservation.as_ref(), patch_size) {
+            apply_reservation.release(size);
+        }
+        let mut task = progress.notify("Applying", weights.apply);
+
+        let mut redownloaded = false;
+        loop {
+            let failure = match (patcher.apply)(&fetched, &entry.dir, &mut task, cancel) {
+                Ok(()) => break,
+                Err(failure) => failure,
+            };
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+
+            // a damaged download is worth fetching once more, a result that doesn't
+            // match its signature would only fail the same way again
+            if failure == butler::Failure::Corrupt && !redownloaded {
+                log::warn!(
+                    "butler reports patch {} as corrupt, downloading it again",
+                    patch.id
+                );
+                redownloaded = true;
+                send_state
+                    .send(WorkerMsg::Status("Downloading File Again...".to_string()))
+                    .unwrap();
+
+                // the old files share their paths with the new ones, get rid of them first
+                drop(fetched);
+                fetched = fetch_patch(
+                    patch,
+                    &downloads_dir,
+                    progress.fetch_again(),
+                    &progress,
+                    settings,
+                    cancel,
+                )?;
+                continue;
+            }
+
+            fetched.patch_file.preserve();
+            fetched.sig_file.preserve();
+            match failure {
+                butler::Failure::Corrupt => log::error!(
+                    "butler failed to apply patch {}, still corrupt after downloading it again",
+                    patch.id
+                ),
+                butler::Failure::Mismatch => log::error!(
+                    "butler failed to apply patch {}, the result does not match its signature",
+                    patch.id
+                ),
+                butler::Failure::Unknown => {
+                    log::error!("butler failed to apply patch {}", patch.id)
+                }
+            }
+            return Err(match failure {
+                butler::Failure::Mismatch => Error::Signature { patch: patch.id },
+                _ => Error::Butler { patch: patch.id },
+            }
+            .into());
+        }
+        task.finish();
+
+        applied.insert(patch.id);
+        if let Some(applied_through) = applied_through(patch_list, &applied) {
+            entry.patch = applied_through as u16;
+        }
+
+        // record every patch as soon as it's applied, so stopping halfway doesn't lose it
+        save_manifest(manifest, &entry, data_dir)?;
+        update_progress.applied += 1;
+        update_progress.bytes_done += patch_size.unwrap_or(0);
+        update_progress.weight_done += weights.total();
+        save_progress(data_dir, &update_progress);
+    }
+    let _ = fs::remove_file(data_dir.join(PROGRESS_FILE));
+
+    // whatever is still waiting to be applied is for patches that are no longer needed
+    if !patch_list.is_empty() {
+        let _ = fs::remove_dir_all(&downloads_dir);
+    }
+
+    // installs from before key files were tracked get their first snapshot here too
+    if !patch_list.is_empty() || entry.key_files.is_empty() {
+        match integrity::snapshot(&entry.dir) {
+            Ok(key_files) => entry.key_files = key_files,
+            Err(e) => log::warn!("could not take stock of the installed files: {}", e),
+        }
+    }
+
+    // persist manifest to disk
+    save_manifest(manifest, &entry, data_dir)
+}
@@ -0,0 +1,216 @@
+// every test binary pulls this in, but only uses part of it
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use applauncher::settings::Settings;
+use applauncher::updater::PatchInfo;
+
+#[derive(Clone)]
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+// just enough of an HTTP/1.1 server to stand in for the update server, every path
+// answers with whatever was registered for it last and anything else is a 404
+pub struct MockServer {
+    url: String,
+    routes: Arc<Mutex<HashMap<String, Response>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+// what a request came in as, for tests that care how the launcher asked
+#[derive(Clone)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+    pub body: Vec<u8>,
+}
+
+impl MockServer {
+    pub fn start() -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = Arc::new(Mutex::new(HashMap::new()));
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_routes = routes.clone();
+        let server_requests = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = server_routes.clone();
+                let requests = server_requests.clone();
+                thread::spawn(move || handle(stream, &routes, &requests));
+            }
+        });
+
+        MockServer {
+            url,
+            routes,
+            requests,
+        }
+    }
+
+    // the launcher's defaults, only pointed at this server
+    pub fn settings(&self) -> Settings {
+        Settings {
+            update_server: self.url.clone(),
+            ..Settings::default()
+        }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn serve(&self, path: &str, status: u16, headers: &[(&str, &str)], body: &[u8]) {
+        self.routes.lock().unwrap().insert(
+            path.to_string(),
+            Response {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                body: body.to_vec(),
+            },
+        );
+    }
+
+    // the patch list as the real server sends it
+    pub fn serve_patch_list(&self, patch_list: &[PatchInfo]) {
+        self.serve(
+            "/api/v0/patch",
+            200,
+            &[("Content-Type", "application/json"), ("X-Api-Version", "0")],
+            serde_json::to_string(patch_list).unwrap().as_bytes(),
+        );
+    }
+
+    // serves a patch and its signature, checksums are taken from what is served
+    pub fn patch(&self, id: u64, patch: &[u8], sig: &[u8]) -> PatchInfo {
+        let patch_path = format!("/files/{}.pwr", id);
+        let sig_path = format!("/files/{}.pwr.sig", id);
+        self.serve(&patch_path, 200, &[], patch);
+        self.serve(&sig_path, 200, &[], sig);
+
+        PatchInfo {
+            id,
+            app: "unnamed-sdvx-clone".into(),
+            platform: "win32".into(),
+            url: format!("{}{}", self.url, patch_path),
+            hash: crc32c::crc32c(patch),
+            sig: format!("{}{}", self.url, sig_path),
+            sig_hash: crc32c::crc32c(sig),
+            size: Some(patch.len() as u64),
+            ..PatchInfo::default()
+        }
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    routes: &Mutex<HashMap<String, Response>>,
+    requests: &Mutex<Vec<Request>>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next().unwrap_or_default().to_string();
+    let target = request_parts.next().unwrap_or_default().to_string();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    // the patch list request carries its parameters as a form body, which has to be drained
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 || header.trim_end().is_empty() {
+            break;
+        }
+        let mut header_parts = header.splitn(2, ':');
+        let name = header_parts.next().unwrap_or_default();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = header_parts
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .parse()
+                .unwrap_or(0);
+        }
+    }
+    let mut request_body = vec![0; content_length];
+    let _ = reader.read_exact(&mut request_body);
+    requests.lock().unwrap().push(Request {
+        method: method.clone(),
+        target,
+        body: request_body,
+    });
+
+    let response = routes
+        .lock()
+        .unwrap()
+        .get(&path)
+        .cloned()
+        .unwrap_or(Response {
+            status: 404,
+            headers: Vec::new(),
+            body: Vec::new(),
+        });
+
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in response.headers.iter() {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let mut stream = stream;
+    let _ = stream.write_all(head.as_bytes());
+    if method != "HEAD" {
+        let _ = stream.write_all(&response.body);
+    }
+}
+
+// a fresh directory per test, tests run in parallel
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir =
+        env::temp_dir()
+            .join("applauncher-tests")
+            .join(format!("{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// downloads are named after their patch, so tests use ids of their own to stay out of each
+// other's way
+pub fn temp_files_left(id: u64) -> bool {
+    let temp_dir = applauncher::paths::temp_path("unnamed-sdvx-clone");
+    [
+        format!("tmp-file-{}.pwr", id),
+        format!("tmp-file-{}.pwr.sig", id),
+    ]
+    .iter()
+    .any(|name| temp_dir.join(name).exists())
+}
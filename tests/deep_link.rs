@@ -0,0 +1,57 @@
+use std::fs;
+use std::net::{Ipv4Addr, TcpStream};
+use std::time::Duration;
+
+use applauncher::deep_link::{self, DeepLink};
+use applauncher::single_instance;
+
+mod common;
+
+#[test]
+fn links_name_the_game_and_optionally_a_target() {
+    assert_eq!(
+        deep_link::parse("applauncher://play/unnamed-sdvx-clone/").unwrap(),
+        DeepLink {
+            app: "unnamed-sdvx-clone".into(),
+            target: None,
+        }
+    );
+    assert_eq!(
+        deep_link::parse("AppLauncher://play/unnamed-sdvx-clone?target=Vulkan%20Build").unwrap(),
+        DeepLink {
+            app: "unnamed-sdvx-clone".into(),
+            target: Some("Vulkan Build".into()),
+        }
+    );
+}
+
+#[test]
+fn anything_else_is_refused() {
+    for uri in [
+        "https://play/unnamed-sdvx-clone",
+        "applauncher://install/unnamed-sdvx-clone",
+        "applauncher://play/",
+        "applauncher://play/unnamed-sdvx-clone/extra",
+    ]
+    .iter()
+    {
+        assert!(deep_link::parse(uri).is_err(), "{}", uri);
+    }
+}
+
+#[test]
+fn a_silent_client_does_not_hold_up_links() {
+    let data_dir = common::test_dir("single-instance");
+    let recv_uri = single_instance::listen(&data_dir).unwrap();
+    let port: u16 = fs::read_to_string(data_dir.join("instance.port"))
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    // connects and never sends a thing
+    let _silent = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+
+    let uri = "applauncher://play/unnamed-sdvx-clone";
+    assert!(single_instance::forward(&data_dir, uri));
+    assert_eq!(recv_uri.recv_timeout(Duration::from_secs(5)).unwrap(), uri);
+}
@@ -0,0 +1,25 @@
+use std::fs;
+
+use applauncher::disk_space::SpaceReservation;
+
+mod common;
+
+#[test]
+fn reservation_shrinks_and_goes_away() {
+    let dir = common::test_dir("disk-space");
+    let path = dir.join("reserved");
+
+    let reservation = SpaceReservation::reserve(path.clone(), 1024 * 1024).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 1024 * 1024);
+
+    reservation.release(256 * 1024);
+    assert_eq!(reservation.reserved(), 768 * 1024);
+    assert_eq!(fs::metadata(&path).unwrap().len(), 768 * 1024);
+
+    // handing back more than is left just empties it
+    reservation.release(u64::MAX);
+    assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+    drop(reservation);
+    assert!(!path.exists());
+}
@@ -0,0 +1,43 @@
+use applauncher::settings::DownloadWindow;
+use chrono::NaiveTime;
+
+fn window(start: &str, end: &str) -> DownloadWindow {
+    DownloadWindow {
+        start: start.into(),
+        end: end.into(),
+    }
+}
+
+fn at(time: &str) -> NaiveTime {
+    NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+}
+
+#[test]
+fn windows_within_a_day() {
+    let window = window("09:00", "17:30");
+    assert!(!window.contains(at("08:59")).unwrap());
+    assert!(window.contains(at("09:00")).unwrap());
+    assert!(window.contains(at("17:29")).unwrap());
+    assert!(!window.contains(at("17:30")).unwrap());
+}
+
+#[test]
+fn windows_past_midnight() {
+    let window = window("23:00", "06:00");
+    assert!(window.contains(at("23:00")).unwrap());
+    assert!(window.contains(at("00:00")).unwrap());
+    assert!(window.contains(at("05:59")).unwrap());
+    assert!(!window.contains(at("06:00")).unwrap());
+    assert!(!window.contains(at("12:00")).unwrap());
+}
+
+#[test]
+fn same_start_and_end_is_the_whole_day() {
+    assert!(window("02:00", "02:00").contains(at("13:37")).unwrap());
+}
+
+#[test]
+fn malformed_times_are_errors() {
+    assert!(window("11pm", "06:00").contains(at("12:00")).is_err());
+    assert!(window("23:00", "25:00").contains(at("12:00")).is_err());
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use applauncher::install_lock::{self, LockInfo, Owner, LOCK_FILE};
+use chrono::Utc;
+
+mod common;
+
+fn write_lock(install_dir: &Path, owner: Owner, pid: u32) {
+    let lock_info = LockInfo {
+        owner,
+        pid,
+        since: Utc::now(),
+    };
+    fs::write(
+        install_dir.join(LOCK_FILE),
+        serde_json::to_string(&lock_info).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn running_game_keeps_the_launcher_out() {
+    let install_dir = common::test_dir("lock-running-game");
+    // this test's own process stands in for the game
+    write_lock(&install_dir, Owner::Game, process::id());
+
+    let e = install_lock::acquire(&install_dir).err().unwrap();
+    assert!(e.to_string().contains("The game is running"));
+    assert!(install_dir.join(LOCK_FILE).exists());
+}
+
+#[test]
+fn stale_lock_is_taken_over_and_released() {
+    let install_dir = common::test_dir("lock-stale");
+    write_lock(&install_dir, Owner::Game, u32::MAX - 1);
+
+    let install_lock = install_lock::acquire(&install_dir).unwrap();
+    let holder = install_lock::holder(&install_dir).unwrap();
+    assert_eq!(holder.owner, Owner::Launcher);
+    assert_eq!(holder.pid, process::id());
+
+    drop(install_lock);
+    assert!(!install_dir.join(LOCK_FILE).exists());
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use applauncher::integrity::{self, FileChecksum};
+use applauncher::settings::Settings;
+use applauncher::updater::{self, CancelToken};
+use sha2::{Digest, Sha256};
+
+use common::MockServer;
+
+mod common;
+
+#[test]
+fn truncated_and_missing_key_files_are_caught() {
+    let install_dir = common::test_dir("integrity-install");
+    fs::create_dir_all(install_dir.join("bin")).unwrap();
+    fs::write(install_dir.join("usc-game.exe"), b"executable").unwrap();
+    fs::write(install_dir.join("bin").join("audio.dll"), b"library").unwrap();
+    fs::write(install_dir.join("Main.cfg"), b"settings").unwrap();
+
+    let key_files = integrity::snapshot(&install_dir).unwrap();
+    assert_eq!(key_files.len(), 2);
+    assert!(integrity::check(&install_dir, &key_files).is_empty());
+
+    // the game rewriting its own settings is no reason to repair anything
+    fs::write(install_dir.join("Main.cfg"), b"other settings").unwrap();
+    fs::write(install_dir.join("usc-game.exe"), b"exe").unwrap();
+    fs::remove_file(install_dir.join("bin").join("audio.dll")).unwrap();
+
+    assert_eq!(
+        integrity::check(&install_dir, &key_files),
+        vec!["bin/audio.dll".to_string(), "usc-game.exe".to_string()]
+    );
+}
+
+#[test]
+fn files_are_checked_against_their_checksums() {
+    let install_dir = common::test_dir("integrity-checksums");
+    fs::create_dir_all(install_dir.join("bin")).unwrap();
+    fs::write(install_dir.join("usc-game.exe"), b"executable").unwrap();
+    fs::write(install_dir.join("bin").join("audio.dll"), b"library").unwrap();
+    fs::write(install_dir.join("Main.cfg"), b"settings").unwrap();
+    // room a crashed update had set aside isn't the player's, nor the game's
+    fs::write(install_dir.join("tmp-file-reserved-7"), b"").unwrap();
+
+    let checksum = |contents: &[u8]| FileChecksum {
+        size: contents.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(contents)),
+        url: None,
+    };
+    let mut checksums = BTreeMap::new();
+    checksums.insert("usc-game.exe".to_string(), checksum(b"executable"));
+    checksums.insert("bin/audio.dll".to_string(), checksum(b"library"));
+    checksums.insert("bin/shaders.dll".to_string(), checksum(b"shaders"));
+
+    let report = integrity::verify_files(&install_dir, &checksums).unwrap();
+    assert_eq!(report.missing, vec!["bin/shaders.dll".to_string()]);
+    assert_eq!(report.extra, vec!["Main.cfg".to_string()]);
+    assert!(report.modified.is_empty());
+
+    // same size, so only the checksum gives it away
+    fs::write(install_dir.join("bin").join("audio.dll"), b"LIBRARY").unwrap();
+    fs::write(install_dir.join("bin").join("shaders.dll"), b"shaders").unwrap();
+    let report = integrity::verify_files(&install_dir, &checksums).unwrap();
+    assert!(report.missing.is_empty());
+    assert_eq!(report.modified, vec!["bin/audio.dll".to_string()]);
+    assert!(report.needs_repair());
+}
+
+#[test]
+fn only_broken_files_are_downloaded_again() {
+    let server = MockServer::start();
+    server.serve("/files/2001/bin/audio.dll", 200, &[], b"library");
+    let install_dir = common::test_dir("integrity-repair");
+    fs::create_dir_all(install_dir.join("bin")).unwrap();
+    fs::write(install_dir.join("usc-game.exe"), b"executable").unwrap();
+    fs::write(install_dir.join("bin").join("audio.dll"), b"LIBRARY").unwrap();
+
+    let checksum = |contents: &[u8], url: Option<String>| FileChecksum {
+        size: contents.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(contents)),
+        url,
+    };
+    let mut checksums = BTreeMap::new();
+    checksums.insert("usc-game.exe".to_string(), checksum(b"executable", None));
+    checksums.insert(
+        "bin/audio.dll".to_string(),
+        checksum(
+            b"library",
+            Some(format!("{}/files/2001/bin/audio.dll", server.url())),
+        ),
+    );
+    checksums.insert(
+        "../outside.dll".to_string(),
+        checksum(
+            b"library",
+            Some(format!("{}/files/2001/bin/audio.dll", server.url())),
+        ),
+    );
+    let settings = Settings {
+        update_server: server.url().into(),
+        ..Settings::default()
+    };
+
+    // nothing outside of the install is ever written to
+    let outside = vec!["../outside.dll".to_string()];
+    assert!(updater::repair_files(
+        &install_dir,
+        &checksums,
+        &outside,
+        &settings,
+        &CancelToken::default()
+    )
+    .is_err());
+
+    // nor is a hash that isn't one, it names the download's temp file
+    let mut bad_hash = checksums.clone();
+    bad_hash.get_mut("bin/audio.dll").unwrap().sha256 = "../../repair".to_string();
+    assert!(updater::repair_files(
+        &install_dir,
+        &bad_hash,
+        &["bin/audio.dll".to_string()],
+        &settings,
+        &CancelToken::default()
+    )
+    .is_err());
+
+    let report = integrity::verify_files(&install_dir, &checksums).unwrap();
+    updater::repair_files(
+        &install_dir,
+        &checksums,
+        &report.modified,
+        &settings,
+        &CancelToken::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read(install_dir.join("bin").join("audio.dll")).unwrap(),
+        b"library"
+    );
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].target, "/files/2001/bin/audio.dll");
+}
@@ -0,0 +1,21 @@
+use std::fs;
+
+use applauncher::logger::{self, LOG_FILE};
+
+mod common;
+
+#[test]
+fn long_logs_are_cut_at_a_whole_line() {
+    let data_dir = common::test_dir("logger-tail");
+    let log: String = (0..100).map(|line| format!("line {}\n", line)).collect();
+    fs::write(data_dir.join(LOG_FILE), &log).unwrap();
+
+    let tail = logger::tail_bytes(&data_dir, 30).unwrap();
+    assert_eq!(
+        tail,
+        "[earlier lines left out]\nline 97\nline 98\nline 99\n"
+    );
+
+    // short enough to show in full
+    assert_eq!(logger::tail_bytes(&data_dir, 4096).unwrap(), log);
+}
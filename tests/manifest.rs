@@ -0,0 +1,59 @@
+use std::fs;
+
+use applauncher::manifest::{AppEntry, InstallManifest, MANIFEST_FILE};
+
+mod common;
+
+#[test]
+fn import_leaves_out_missing_installs() {
+    let export_dir = common::test_dir("manifest-export");
+    let install_dir = common::test_dir("manifest-install");
+
+    let mut exported = InstallManifest::default();
+    exported.games.insert(
+        "unnamed-sdvx-clone".into(),
+        AppEntry {
+            dir: install_dir.clone(),
+            patch: 12,
+            ..AppEntry::default()
+        },
+    );
+    exported.games.insert(
+        "another-game".into(),
+        AppEntry {
+            dir: export_dir.join("gone"),
+            patch: 3,
+            ..AppEntry::default()
+        },
+    );
+    let export_path = export_dir.join("games.toml");
+    exported.export(&export_path).unwrap();
+
+    let mut manifest = InstallManifest::default();
+    let missing = manifest.import(&export_path).unwrap();
+
+    assert_eq!(missing, vec!["another-game".to_string()]);
+    assert_eq!(manifest.games.len(), 1);
+    assert_eq!(manifest.games["unnamed-sdvx-clone"].patch, 12);
+    assert_eq!(manifest.games["unnamed-sdvx-clone"].dir, install_dir);
+}
+
+#[test]
+fn app_ids_are_normalized_on_load() {
+    let data_dir = common::test_dir("manifest-normalize");
+    fs::write(
+        data_dir.join(MANIFEST_FILE),
+        "[games.\" Unnamed-SDVX-Clone \"]\ndir = \"C:\\\\Games\\\\USC\"\npatch = 7\n\n\
+         [games.unnamed-sdvx-clone]\ndir = \"C:\\\\Games\\\\USC-old\"\npatch = 3\n",
+    )
+    .unwrap();
+
+    let manifest = InstallManifest::load(&data_dir).unwrap();
+    assert_eq!(manifest.games.len(), 1);
+    assert_eq!(manifest.game("UNNAMED-sdvx-clone ").unwrap().patch, 7);
+
+    // the migration is written back, loading again finds nothing left to do
+    let mut reloaded = InstallManifest::load(&data_dir).unwrap();
+    assert!(!reloaded.normalize_keys());
+    assert_eq!(reloaded.games["unnamed-sdvx-clone"].patch, 7);
+}
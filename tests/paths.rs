@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+use applauncher::paths;
+
+#[test]
+fn extended_length_paths() {
+    assert_eq!(
+        paths::extended_length(Path::new(r"C:\Games\USC")),
+        PathBuf::from(r"\\?\C:\Games\USC")
+    );
+    assert_eq!(
+        paths::extended_length(Path::new("C:/Games/USC")),
+        PathBuf::from(r"\\?\C:\Games\USC")
+    );
+    assert_eq!(
+        paths::extended_length(Path::new(r"\\nas\games\USC")),
+        PathBuf::from(r"\\?\UNC\nas\games\USC")
+    );
+    // already extended, or relative and so nothing to anchor the prefix to
+    assert_eq!(
+        paths::extended_length(Path::new(r"\\?\C:\Games\USC")),
+        PathBuf::from(r"\\?\C:\Games\USC")
+    );
+    assert_eq!(
+        paths::extended_length(Path::new(r"tools\butler")),
+        PathBuf::from(r"tools\butler")
+    );
+}
+
+#[test]
+fn only_long_paths_are_extended() {
+    let short = Path::new(r"C:\Games\USC");
+    assert_eq!(paths::long_path_safe(short), short);
+
+    let long = PathBuf::from(format!(r"C:\Games\{}", "deeply-nested\\".repeat(20)));
+    assert!(paths::is_long_install_dir(&long));
+    assert!(paths::long_path_safe(&long).starts_with(r"\\?\C:\"));
+}
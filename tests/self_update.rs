@@ -0,0 +1,59 @@
+use applauncher::self_update::{is_update, is_windows_asset, parse_tag};
+use applauncher::settings::ReleaseChannel::{Beta, Stable};
+use semver::Version;
+
+fn version(version: &str) -> Version {
+    Version::parse(version).unwrap()
+}
+
+#[test]
+fn comparison_matrix() {
+    // current, candidate, offered on stable, offered on beta
+    let matrix = [
+        ("0.1.4", "0.1.5", true, true),
+        ("0.1.4", "0.1.4", false, false),
+        ("0.1.4", "0.1.3", false, false),
+        ("0.1.4", "0.2.0-beta.1", false, true),
+        ("0.2.0-beta.1", "0.2.0-beta.2", false, true),
+        ("0.2.0-beta.2", "0.2.0-beta.1", false, false),
+        ("0.2.0-beta.1", "0.2.0", true, true),
+        ("0.2.0-beta.1", "0.1.4", false, false),
+        ("0.1.4", "0.1.4+win64", false, false),
+        ("0.1.4+win64", "0.1.5+win64", true, true),
+    ];
+
+    for (current, candidate, stable, beta) in matrix.iter() {
+        let (current, candidate) = (version(current), version(candidate));
+        assert_eq!(
+            is_update(&current, &candidate, Stable),
+            *stable,
+            "{} -> {} on stable",
+            current,
+            candidate
+        );
+        assert_eq!(
+            is_update(&current, &candidate, Beta),
+            *beta,
+            "{} -> {} on beta",
+            current,
+            candidate
+        );
+    }
+}
+
+#[test]
+fn tags_parse_with_or_without_a_v() {
+    assert_eq!(parse_tag("v0.1.4"), Some(version("0.1.4")));
+    assert_eq!(parse_tag("0.2.0-beta.1"), Some(version("0.2.0-beta.1")));
+    assert_eq!(parse_tag("nightly"), None);
+}
+
+#[test]
+fn only_windows_assets_count_as_windows() {
+    assert!(is_windows_asset("applauncher-v0.1.4-windows-x64.zip"));
+    assert!(is_windows_asset("AppLauncher_Win64.exe"));
+    assert!(is_windows_asset("applauncher-win.zip"));
+    assert!(!is_windows_asset("applauncher-v0.1.4-darwin-x64.zip"));
+    assert!(!is_windows_asset("applauncher-v0.1.4-linux-x64.zip"));
+    assert!(!is_windows_asset("winamp-skin.zip"));
+}
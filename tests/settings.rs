@@ -0,0 +1,141 @@
+use std::fs;
+use std::time::Duration;
+
+use applauncher::settings::{Settings, SETTINGS_FILE};
+
+mod common;
+
+#[test]
+fn retry_settings_are_clamped() {
+    let defaults = Settings::default();
+    assert_eq!(defaults.download_retries(), 2);
+    assert_eq!(defaults.retry_backoff(), Duration::from_millis(0));
+
+    let settings = Settings {
+        download_retries: 1000,
+        retry_backoff_ms: u64::MAX,
+        ..Settings::default()
+    };
+    assert_eq!(settings.download_retries(), 10);
+    assert_eq!(settings.retry_backoff(), Duration::from_secs(30));
+}
+
+#[test]
+fn self_update_check_can_be_turned_off() {
+    assert!(Settings::default().self_update_check());
+
+    let data_dir = common::test_dir("settings-self-update");
+    fs::write(data_dir.join(SETTINGS_FILE), "self_update_check = false\n").unwrap();
+    assert!(!Settings::load(&data_dir).unwrap().self_update_check());
+}
+
+#[test]
+fn provisioned_settings_sit_under_the_users() {
+    let data_dir = common::test_dir("settings-provisioned");
+    let admin_path = data_dir.join("admin.toml");
+    fs::write(
+        &admin_path,
+        "update_server = \"https://updates.example.com\"\nallowed_hosts = [\"cdn.example.com\"]\nproxy = \"http://proxy.example.com:8080\"\n",
+    )
+    .unwrap();
+    fs::write(
+        data_dir.join(SETTINGS_FILE),
+        "proxy = \"http://localhost:3128\"\nkeep_temp = true\n",
+    )
+    .unwrap();
+
+    let settings = Settings::load_provisioned(&data_dir, Some(&admin_path)).unwrap();
+    assert_eq!(settings.server(), "https://updates.example.com");
+    assert_eq!(settings.allowed_hosts, vec!["cdn.example.com".to_string()]);
+    assert_eq!(settings.proxy.as_deref(), Some("http://localhost:3128"));
+    assert!(settings.keep_temp);
+
+    // only what the user set ends up in their file
+    settings.save(&data_dir).unwrap();
+    let saved = fs::read_to_string(data_dir.join(SETTINGS_FILE)).unwrap();
+    assert!(!saved.contains("update_server"));
+    assert!(!saved.contains("cdn.example.com"));
+    assert!(saved.contains("http://localhost:3128"));
+}
+
+#[test]
+fn saving_does_not_pin_the_defaults() {
+    let data_dir = common::test_dir("settings-pinned");
+    let admin_path = data_dir.join("admin.toml");
+    fs::write(data_dir.join(SETTINGS_FILE), "keep_temp = true\n").unwrap();
+
+    let mut settings = Settings::load_provisioned(&data_dir, Some(&admin_path)).unwrap();
+    settings
+        .games
+        .entry("unnamed-sdvx-clone".into())
+        .or_default()
+        .accepted_eula = Some("accepted".into());
+    settings.save(&data_dir).unwrap();
+
+    // provisioned after the user's file was written, it still reaches them
+    fs::write(
+        &admin_path,
+        "update_server = \"https://updates.example.com\"\nproxy = \"http://proxy.example.com:8080\"\n",
+    )
+    .unwrap();
+    let settings = Settings::load_provisioned(&data_dir, Some(&admin_path)).unwrap();
+    assert_eq!(settings.server(), "https://updates.example.com");
+    assert_eq!(
+        settings.proxy.as_deref(),
+        Some("http://proxy.example.com:8080")
+    );
+    assert!(settings.keep_temp);
+    assert_eq!(
+        settings.game("unnamed-sdvx-clone").accepted_eula.as_deref(),
+        Some("accepted")
+    );
+}
+
+#[test]
+fn data_usage_starts_over_every_month() {
+    let mut settings = Settings::default();
+    assert_eq!(settings.add_data_usage(100, "2026-09"), 100);
+    assert_eq!(settings.add_data_usage(50, "2026-09"), 150);
+    assert_eq!(settings.add_data_usage(10, "2026-10"), 10);
+}
+
+#[test]
+fn patch_list_paths_are_validated() {
+    let data_dir = common::test_dir("settings-patch-list-path");
+    for (path, expected) in [
+        ("/api/v1/patch", "/api/v1/patch"),
+        ("api/v1/patch", "/api/v0/patch"),
+        ("/api/v1/patch?app=1", "/api/v0/patch"),
+        ("https://example.com/api/v1/patch", "/api/v0/patch"),
+    ]
+    .iter()
+    {
+        fs::write(
+            data_dir.join(SETTINGS_FILE),
+            format!("[patch_list_request]\npath = {:?}\n", path),
+        )
+        .unwrap();
+        let settings = Settings::load(&data_dir).unwrap();
+        assert_eq!(settings.patch_list_request.path, *expected);
+    }
+}
+
+#[test]
+fn file_manifest_paths_are_validated() {
+    let data_dir = common::test_dir("settings-file-manifest-path");
+    for (path, expected) in [
+        ("/api/v1/files", "/api/v1/files"),
+        ("api/v1/files", "/api/v0/files"),
+        ("/api/v1/files#top", "/api/v0/files"),
+    ]
+    .iter()
+    {
+        fs::write(
+            data_dir.join(SETTINGS_FILE),
+            format!("file_manifest_path = {:?}\n", path),
+        )
+        .unwrap();
+        let settings = Settings::load(&data_dir).unwrap();
+        assert_eq!(settings.file_manifest_path, *expected);
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+
+use applauncher::updater::{self, PatchInfo, SigFormat};
+
+mod common;
+
+// a key pair and a prehashed signature made with the minisign crate, trusted comment "patch 1"
+const PUBLIC_KEY: &str = "RWTiussTec4oD2XR1xALV6YtgaArrcu8YwKuj0lrvlbJXtvgJI6/4m7V";
+const PATCH: &[u8] = b"pretend this is a butler patch\n";
+const MINISIG: &str = "untrusted comment: signature from rsign secret key
+RUTiussTec4oD0yseAbmuGnV7EdaDHvu5yvdNScM3YTtlKOjak7/G20CNRLGBIhwjdCTEcik8ka1MzIOeNlFY8Vbj663BS3u0wk=
+trusted comment: patch 1
+zXyKTsv/OYt5SQYIj+MY+ON0i2DzCXgqKaLYvaVTYLjE8Xp3SLsat7kz/nRPKfvII9X2K6KYCOyXIkr8Qb0FAA==
+";
+// a different key, as if the patch had been signed by someone else
+const OTHER_PUBLIC_KEY: &str = "RWRcSs51lUX37d8Nkz50xY+54gBTHhSo9M/XQdXB5PK9NQW03bSioJUb";
+
+#[test]
+fn minisign_signatures_are_checked() {
+    let dir = common::test_dir("signature-minisign");
+    let patch_path = dir.join("1.pwr");
+    let sig_path = dir.join("1.pwr.sig");
+    fs::write(&patch_path, PATCH).unwrap();
+    fs::write(&sig_path, MINISIG).unwrap();
+
+    updater::verify_minisign(&patch_path, &sig_path, PUBLIC_KEY).unwrap();
+    assert!(updater::verify_minisign(&patch_path, &sig_path, OTHER_PUBLIC_KEY).is_err());
+
+    fs::write(&patch_path, b"pretend this is a tampered patch\n").unwrap();
+    assert!(updater::verify_minisign(&patch_path, &sig_path, PUBLIC_KEY).is_err());
+}
+
+#[test]
+fn signatures_are_wharf_unless_the_server_says_otherwise() {
+    let patch = r#"{"id":1,"app":"unnamed-sdvx-clone","name":"1","platform":"win32","issuer":0,"url":"https://orchestra.fm/1.pwr","hash":0,"sig":"https://orchestra.fm/1.pwr.sig","sig_hash":0,"arch":"x64"}"#;
+    let wharf: PatchInfo = serde_json::from_str(patch).unwrap();
+    assert_eq!(wharf.sig_format, SigFormat::Wharf);
+
+    let patch = patch.replace(r#""arch""#, r#""sig_format":"minisign","arch""#);
+    let minisign: PatchInfo = serde_json::from_str(&patch).unwrap();
+    assert_eq!(minisign.sig_format, SigFormat::Minisign);
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use applauncher::install_lock::{LockInfo, Owner, LOCK_FILE};
+use applauncher::manifest::{AppEntry, InstallManifest};
+use applauncher::settings::LaunchTarget;
+use applauncher::uninstall;
+use chrono::Utc;
+
+mod common;
+
+fn install(data_dir: &Path, install_dir: &Path) {
+    fs::write(install_dir.join("usc-game.exe"), b"game").unwrap();
+    fs::create_dir_all(install_dir.join("songs")).unwrap();
+    fs::write(install_dir.join("songs").join("chart.ksh"), b"chart").unwrap();
+
+    let mut manifest = InstallManifest::default();
+    manifest.games.insert(
+        "unnamed-sdvx-clone".into(),
+        AppEntry {
+            dir: install_dir.to_path_buf(),
+            patch: 7,
+            ..AppEntry::default()
+        },
+    );
+    manifest.save(data_dir).unwrap();
+}
+
+#[test]
+fn uninstall_removes_the_install_and_its_entry() {
+    let data_dir = common::test_dir("uninstall-data");
+    let install_dir = common::test_dir("uninstall-install");
+    install(&data_dir, &install_dir);
+
+    let removed =
+        uninstall::uninstall(&data_dir, "unnamed-sdvx-clone", &[LaunchTarget::default()]).unwrap();
+
+    assert_eq!(removed, install_dir);
+    assert!(!install_dir.exists());
+    let manifest = InstallManifest::load(&data_dir).unwrap();
+    assert!(manifest.game("unnamed-sdvx-clone").is_none());
+}
+
+#[test]
+fn running_game_is_not_uninstalled() {
+    let data_dir = common::test_dir("uninstall-running-data");
+    let install_dir = common::test_dir("uninstall-running-install");
+    install(&data_dir, &install_dir);
+    // this test's own process stands in for the game
+    let lock_info = LockInfo {
+        owner: Owner::Game,
+        pid: process::id(),
+        since: Utc::now(),
+    };
+    fs::write(
+        install_dir.join(LOCK_FILE),
+        serde_json::to_string(&lock_info).unwrap(),
+    )
+    .unwrap();
+
+    let e = uninstall::uninstall(&data_dir, "unnamed-sdvx-clone", &[LaunchTarget::default()])
+        .err()
+        .unwrap();
+
+    assert!(e.to_string().contains("The game is running"));
+    assert!(install_dir.join("songs").join("chart.ksh").exists());
+    let manifest = InstallManifest::load(&data_dir).unwrap();
+    assert_eq!(manifest.game("unnamed-sdvx-clone").unwrap().patch, 7);
+}
+
+#[test]
+fn folders_without_the_game_are_left_alone() {
+    let data_dir = common::test_dir("uninstall-elsewhere-data");
+    let install_dir = common::test_dir("uninstall-elsewhere-install");
+    install(&data_dir, &install_dir);
+    fs::remove_file(install_dir.join("usc-game.exe")).unwrap();
+
+    let e = uninstall::uninstall(&data_dir, "unnamed-sdvx-clone", &[LaunchTarget::default()])
+        .err()
+        .unwrap();
+
+    assert!(e.to_string().contains("does not seem to be installed"));
+    assert!(install_dir.join("songs").join("chart.ksh").exists());
+    let manifest = InstallManifest::load(&data_dir).unwrap();
+    assert_eq!(manifest.game("unnamed-sdvx-clone").unwrap().patch, 7);
+}
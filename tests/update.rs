@@ -0,0 +1,1192 @@
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use applauncher::error::{self, ErrorKind};
+use applauncher::manifest::{AppEntry, InstallManifest};
+use applauncher::settings::{ParamEncoding, PatchListRequest, RequestMethod, Settings};
+use applauncher::updater::{self, CancelToken, FetchedPatch, PatchInfo, Patcher, Task, WorkerMsg};
+use applauncher::{backups, butler, install_lock, paths};
+use crossbeam::channel::unbounded;
+use eyre::Result;
+use sha2::{Digest, Sha256};
+
+mod common;
+
+use common::MockServer;
+
+fn supported() -> Result<()> {
+    Ok(())
+}
+
+// stands in for butler by copying the patch into the install directory, a patch that
+// says "broken" is rejected the way butler rejects one that doesn't match its signature
+fn copy_patch(
+    fetched: &FetchedPatch,
+    dir: &Path,
+    task: &mut Task,
+    _cancel: &CancelToken,
+) -> Result<(), butler::Failure> {
+    let patch = fs::read(&fetched.patch_file.path).map_err(|_| butler::Failure::Unknown)?;
+    if patch == b"broken" {
+        return Err(butler::Failure::Mismatch);
+    }
+
+    let file_name = fetched.patch_file.path.file_name().unwrap();
+    fs::write(dir.join(file_name), patch).map_err(|_| butler::Failure::Unknown)?;
+    task.finish();
+    Ok(())
+}
+
+// the window being closed while butler is busy, butler is left to fail on its own
+fn shut_down_midway(
+    _fetched: &FetchedPatch,
+    _dir: &Path,
+    _task: &mut Task,
+    cancel: &CancelToken,
+) -> Result<(), butler::Failure> {
+    cancel.stop();
+    Err(butler::Failure::Unknown)
+}
+
+const COPY_PATCHER: Patcher = Patcher {
+    ensure_supported: supported,
+    apply: copy_patch,
+};
+
+fn new_manifest(install_dir: &Path, patch: u16) -> InstallManifest {
+    let mut manifest = InstallManifest::default();
+    manifest.games.insert(
+        "unnamed-sdvx-clone".into(),
+        AppEntry {
+            dir: install_dir.to_path_buf(),
+            patch,
+            ..AppEntry::default()
+        },
+    );
+    manifest
+}
+
+fn installed_patch(data_dir: &Path) -> u16 {
+    InstallManifest::load(data_dir).unwrap().games["unnamed-sdvx-clone"].patch
+}
+
+fn run_update(
+    server: &MockServer,
+    manifest: &mut InstallManifest,
+    data_dir: &Path,
+    settings: &Settings,
+    patcher: Patcher,
+    cancel: &CancelToken,
+) -> Result<()> {
+    let installed = manifest.games["unnamed-sdvx-clone"].patch;
+    let patch_list = updater::fetch_patch_list(&server.settings(), installed, None, None)?;
+    let (send_state, _recv_state) = unbounded();
+    updater::update(
+        &patch_list,
+        manifest,
+        data_dir,
+        settings,
+        patcher,
+        &send_state,
+        cancel,
+    )
+}
+
+#[test]
+fn applies_every_patch_and_records_it() {
+    let server = MockServer::start();
+    let first = server.patch(101, b"first patch", b"first signature");
+    let second = server.patch(102, b"second patch", b"second signature");
+    server.serve_patch_list(&[first, second]);
+
+    let data_dir = common::test_dir("applies-every-patch-data");
+    let install_dir = common::test_dir("applies-every-patch-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+    // what an update that crashed had set aside
+    fs::write(install_dir.join("tmp-file-reserved-100"), b"reserved").unwrap();
+
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    assert_eq!(installed_patch(&data_dir), 102);
+    assert!(install_dir.join("tmp-file-101.pwr").exists());
+    assert!(install_dir.join("tmp-file-102.pwr").exists());
+    assert!(!install_dir.join("tmp-file-reserved-100").exists());
+    assert!(!install_dir.join("tmp-file-reserved-102").exists());
+    assert!(!common::temp_files_left(101));
+    assert!(!common::temp_files_left(102));
+}
+
+#[test]
+fn checksum_mismatch_stops_before_applying() {
+    let server = MockServer::start();
+    let mut patch = server.patch(201, b"patch", b"signature");
+    patch.hash = patch.hash.wrapping_add(1);
+    server.serve_patch_list(&[patch]);
+
+    let data_dir = common::test_dir("checksum-mismatch-data");
+    let install_dir = common::test_dir("checksum-mismatch-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+
+    let e = run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap_err();
+
+    assert!(e.to_string().contains("CRC32"));
+    assert_eq!(error::kind(&e), ErrorKind::Checksum);
+    assert!(!InstallManifest::exists(&data_dir));
+    assert!(!install_dir.join("tmp-file-201.pwr").exists());
+    assert!(!common::temp_files_left(201));
+}
+
+#[test]
+fn rejected_patch_keeps_the_last_applied_one() {
+    let server = MockServer::start();
+    let first = server.patch(301, b"first patch", b"first signature");
+    let second = server.patch(302, b"broken", b"second signature");
+    server.serve_patch_list(&[first, second]);
+
+    let data_dir = common::test_dir("rejected-patch-data");
+    let install_dir = common::test_dir("rejected-patch-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+
+    let e = run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap_err();
+
+    assert!(e.to_string().contains("failed to apply patch 302"));
+    assert_eq!(error::kind(&e), ErrorKind::Signature);
+    assert_eq!(installed_patch(&data_dir), 301);
+}
+
+#[test]
+fn checksums_on_the_pool_keep_the_apply_order() {
+    let server = MockServer::start();
+    let first = server.patch(311, b"first patch", b"first signature");
+    let second = server.patch(312, b"second patch", b"second signature");
+    let mut damaged = server.patch(313, b"third patch", b"third signature");
+    damaged.hash = damaged.hash.wrapping_add(1);
+    let fourth = server.patch(314, b"fourth patch", b"fourth signature");
+    server.serve_patch_list(&[first, second, damaged, fourth]);
+
+    let data_dir = common::test_dir("checksum-pool-data");
+    let install_dir = common::test_dir("checksum-pool-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+
+    // several patches downloaded ahead and checked at once, applied in order all the same
+    let settings = Settings {
+        prefetch_depth: 3,
+        checksum_threads: 2,
+        ..Settings::default()
+    };
+    let e = run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &settings,
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap_err();
+
+    assert_eq!(error::kind(&e), ErrorKind::Checksum);
+    assert_eq!(installed_patch(&data_dir), 312);
+    assert!(install_dir.join("tmp-file-312.pwr").exists());
+    assert!(!install_dir.join("tmp-file-313.pwr").exists());
+    assert!(!install_dir.join("tmp-file-314.pwr").exists());
+}
+
+#[test]
+fn other_games_survive_a_manifest_save() {
+    let server = MockServer::start();
+    let patch = server.patch(401, b"patch", b"signature");
+    server.serve_patch_list(&[patch]);
+
+    let data_dir = common::test_dir("other-games-data");
+    let install_dir = common::test_dir("other-games-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+    manifest.games.insert(
+        "another-game".into(),
+        AppEntry {
+            dir: data_dir.join("another-game"),
+            patch: 7,
+            ..AppEntry::default()
+        },
+    );
+
+    // without prefetching, downloads happen on the same thread as applying
+    let settings = Settings {
+        prefetch_depth: 0,
+        ..Settings::default()
+    };
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &settings,
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    let saved = InstallManifest::load(&data_dir).unwrap();
+    assert_eq!(saved.games.len(), 2);
+    assert_eq!(saved.games["unnamed-sdvx-clone"].patch, 401);
+    assert_eq!(saved.games["another-game"].patch, 7);
+}
+
+#[test]
+fn shutdown_midway_leaves_no_temp_files() {
+    let server = MockServer::start();
+    let patch = server.patch(501, b"patch", b"signature");
+    server.serve_patch_list(&[patch]);
+
+    let data_dir = common::test_dir("shutdown-data");
+    let install_dir = common::test_dir("shutdown-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+    let cancel = CancelToken::default();
+
+    // a shutdown isn't an error, the update just stops where it is
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        Patcher {
+            ensure_supported: supported,
+            apply: shut_down_midway,
+        },
+        &cancel,
+    )
+    .unwrap();
+
+    assert!(cancel.is_cancelled());
+    assert!(!InstallManifest::exists(&data_dir));
+    assert!(!common::temp_files_left(501));
+}
+
+#[test]
+fn only_the_newest_backups_are_kept() {
+    let server = MockServer::start();
+    let data_dir = common::test_dir("backups-data");
+    let install_dir = common::test_dir("backups-install");
+    let mut manifest = new_manifest(&install_dir, 600);
+    let backups_dir = backups::backups_dir(&data_dir, "unnamed-sdvx-clone");
+
+    for id in 601..=603 {
+        let patch = server.patch(id, b"patch", b"signature");
+        server.serve_patch_list(&[patch]);
+        run_update(
+            &server,
+            &mut manifest,
+            &data_dir,
+            &Settings {
+                patch_backups: 2,
+                ..Settings::default()
+            },
+            COPY_PATCHER,
+            &CancelToken::default(),
+        )
+        .unwrap();
+    }
+
+    // each backup is the install as it was before the patch, without the one being applied
+    assert_eq!(backups::list(&backups_dir), vec![601, 602]);
+    assert!(backups_dir.join("602").join("tmp-file-602.pwr").exists());
+    assert!(!backups_dir.join("602").join("tmp-file-603.pwr").exists());
+}
+
+#[test]
+fn restoring_a_backup_undoes_the_update() {
+    let server = MockServer::start();
+    let data_dir = common::test_dir("restore-data");
+    let install_dir = common::test_dir("restore-install");
+    let mut manifest = new_manifest(&install_dir, 700);
+    let backups_dir = backups::backups_dir(&data_dir, "unnamed-sdvx-clone");
+
+    for id in 701..=702 {
+        let patch = server.patch(id, b"patch", b"signature");
+        server.serve_patch_list(&[patch]);
+        run_update(
+            &server,
+            &mut manifest,
+            &data_dir,
+            &Settings {
+                patch_backups: 2,
+                ..Settings::default()
+            },
+            COPY_PATCHER,
+            &CancelToken::default(),
+        )
+        .unwrap();
+    }
+
+    // the newest backup comes back, the files the last patch brought are gone
+    assert_eq!(backups::restore(&backups_dir, &install_dir).unwrap(), 701);
+    assert!(install_dir.join("tmp-file-701.pwr").exists());
+    assert!(!install_dir.join("tmp-file-702.pwr").exists());
+    assert!(!install_dir.join(install_lock::LOCK_FILE).exists());
+    assert!(backups_dir.join("701").join("tmp-file-701.pwr").exists());
+}
+
+#[test]
+fn cancelled_download_leaves_nothing_behind() {
+    let server = MockServer::start();
+    let patch = server.patch(801, &vec![0; 1024 * 1024], b"signature");
+
+    let (send_state, _recv_state) = unbounded();
+    let weights = updater::task_weights(&[patch.size]);
+    let progress = updater::TaskProgress::new(send_state, &weights);
+    let cancel = CancelToken::default();
+    cancel.stop();
+
+    let e = updater::download_patch(
+        &patch,
+        weights[0],
+        &progress,
+        &Settings {
+            download_buffer_kib: 8,
+            ..Settings::default()
+        },
+        &cancel,
+    )
+    .err()
+    .unwrap();
+    assert!(e.downcast_ref::<updater::Cancelled>().is_some());
+    assert!(!common::temp_files_left(801));
+}
+
+#[test]
+fn download_speed_is_reported_in_batches() {
+    let server = MockServer::start();
+    let patch = server.patch(811, &vec![0; 1024 * 1024], b"signature");
+
+    let (send_state, recv_state) = unbounded();
+    let weights = updater::task_weights(&[patch.size]);
+    let progress = updater::TaskProgress::new(send_state, &weights);
+    let settings = Settings {
+        download_buffer_kib: 8,
+        ..Settings::default()
+    };
+    let fetched = updater::download_patch(
+        &patch,
+        weights[0],
+        &progress,
+        &settings,
+        &CancelToken::default(),
+    )
+    .unwrap();
+    drop(fetched);
+
+    // a hundred and more reads, only a few of them make it to the user interface
+    let downloaded: Vec<u64> = recv_state
+        .try_iter()
+        .filter_map(|msg| match msg {
+            WorkerMsg::Downloaded(bytes) => Some(bytes),
+            _ => None,
+        })
+        .collect();
+    assert!(downloaded.len() < 16);
+    assert_eq!(
+        downloaded.iter().sum::<u64>(),
+        1024 * 1024 + b"signature".len() as u64
+    );
+}
+
+#[test]
+fn incompatible_server_is_refused() {
+    let server = MockServer::start();
+    server.serve("/api/v0/patch", 200, &[("X-Api-Version", "1")], b"[]");
+
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    assert!(e.to_string().contains("no longer compatible"));
+}
+
+#[test]
+fn server_errors_are_reported() {
+    let server = MockServer::start();
+    server.serve("/api/v0/patch", 503, &[], b"<html>down</html>");
+
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    assert!(e.to_string().contains("returned an error"));
+    assert_eq!(error::kind(&e), ErrorKind::Network);
+}
+
+#[test]
+fn retired_launchers_are_turned_away() {
+    let server = MockServer::start();
+    let body = r#"{"patches": [], "min_client_version": "v999.0.0"}"#;
+    server.serve(
+        "/api/v0/patch",
+        200,
+        &[("X-Api-Version", "0")],
+        body.as_bytes(),
+    );
+
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    let too_old = e.downcast_ref::<updater::ClientTooOld>().unwrap();
+    assert_eq!(too_old.required, semver::Version::new(999, 0, 0));
+}
+
+#[test]
+fn supported_launchers_get_their_patches() {
+    let server = MockServer::start();
+    let patch = server.patch(701, b"patch", b"signature");
+    let body = serde_json::json!({ "patches": [patch], "min_client_version": "0.0.1" });
+    server.serve(
+        "/api/v0/patch",
+        200,
+        &[("X-Api-Version", "0")],
+        body.to_string().as_bytes(),
+    );
+
+    let patch_list = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap();
+    assert_eq!(patch_list.len(), 1);
+    assert_eq!(patch_list[0].id, 701);
+}
+
+#[test]
+fn patch_list_request_follows_the_settings() {
+    let server = MockServer::start();
+    server.serve_patch_list(&[]);
+    let params = "app=unnamed-sdvx-clone&platform=win32&version=5";
+
+    for (method, encoding) in [
+        (RequestMethod::Get, ParamEncoding::Form),
+        (RequestMethod::Post, ParamEncoding::Query),
+    ]
+    .iter()
+    {
+        let request = PatchListRequest {
+            method: *method,
+            params: *encoding,
+            ..PatchListRequest::default()
+        };
+        updater::fetch_patch_list(
+            &Settings {
+                patch_list_request: request,
+                ..server.settings()
+            },
+            5,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].target, "/api/v0/patch");
+    assert_eq!(requests[0].body, params.as_bytes());
+    assert_eq!(requests[1].method, "POST");
+    assert_eq!(requests[1].target, format!("/api/v0/patch?{}", params));
+    assert!(requests[1].body.is_empty());
+}
+
+#[test]
+fn retry_after_is_honored_within_reason() {
+    assert_eq!(updater::retry_delay(Some("12")), Duration::from_secs(12));
+    assert_eq!(updater::retry_delay(Some("86400")), Duration::from_secs(60));
+    assert_eq!(updater::retry_delay(None), Duration::from_secs(5));
+    assert_eq!(updater::retry_delay(Some("soon")), Duration::from_secs(5));
+    // dates in the past mean right away
+    assert_eq!(
+        updater::retry_delay(Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+        Duration::from_secs(0)
+    );
+}
+
+#[test]
+fn rate_limiting_gives_up_eventually() {
+    let server = MockServer::start();
+    server.serve("/api/v0/patch", 429, &[("Retry-After", "0")], b"");
+
+    let (send_state, recv_state) = unbounded();
+    let e = updater::fetch_patch_list(&server.settings(), 0, Some(&send_state), None).unwrap_err();
+    assert!(e.to_string().contains("too busy"));
+    assert_eq!(server.requests().len(), 6);
+    assert!(recv_state.try_iter().any(|worker_msg| matches!(
+        worker_msg,
+        WorkerMsg::Status(status) if status.starts_with("Server busy")
+    )));
+}
+
+#[test]
+fn transport_digests_catch_damage_on_the_way() {
+    let server = MockServer::start();
+    let patch = server.patch(901, b"patch", b"signature");
+    let sha256 = base64::encode(Sha256::digest(b"patch"));
+    server.serve(
+        "/files/901.pwr",
+        200,
+        &[("Digest", &format!("SHA-256={}", sha256))],
+        b"patch",
+    );
+    // the signature claims to be something else, it's downloaded again but the patch list
+    // checksum still gets the final word
+    server.serve(
+        "/files/901.pwr.sig",
+        200,
+        &[("Content-MD5", "XrY7u+Ae7tCTyyK7j1rNww==")],
+        b"signature",
+    );
+    server.serve_patch_list(&[patch]);
+
+    let data_dir = common::test_dir("transport-digest-data");
+    let install_dir = common::test_dir("transport-digest-install");
+    let mut manifest = new_manifest(&install_dir, 0);
+
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    let downloads = |target: &str| {
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.method == "GET" && request.target == target)
+            .count()
+    };
+    assert_eq!(installed_patch(&data_dir), 901);
+    assert_eq!(downloads("/files/901.pwr"), 1);
+    assert_eq!(downloads("/files/901.pwr.sig"), 3);
+}
+
+#[test]
+fn patches_apply_from_a_local_folder() {
+    // a patch list as the server sends it, next to the files it points at
+    let server = MockServer::start();
+    let mut patches = vec![
+        server.patch(1001, b"first patch", b"first signature"),
+        server.patch(1002, b"second patch", b"second signature"),
+        server.patch(1003, b"third patch", b"third signature"),
+    ];
+    // a hand written list may spell the game and platform differently
+    patches[2].app = "Unnamed-SDVX-Clone".into();
+    patches[2].platform = "Win32".into();
+    let local_dir = common::test_dir("local-patches");
+    for (id, contents) in [(1002, "second"), (1003, "third")].iter() {
+        fs::write(
+            local_dir.join(format!("{}.pwr", id)),
+            format!("{} patch", contents),
+        )
+        .unwrap();
+        fs::write(
+            local_dir.join(format!("{}.pwr.sig", id)),
+            format!("{} signature", contents),
+        )
+        .unwrap();
+    }
+    fs::write(
+        local_dir.join(updater::LOCAL_PATCH_LIST),
+        serde_json::to_string(&patches).unwrap(),
+    )
+    .unwrap();
+    drop(server);
+
+    let data_dir = common::test_dir("local-patches-data");
+    let install_dir = common::test_dir("local-patches-install");
+    let mut manifest = new_manifest(&install_dir, 1001);
+
+    let patch_list = updater::local_patch_list(&local_dir, 1001).unwrap();
+    assert_eq!(patch_list.len(), 2);
+    let (send_state, _recv_state) = unbounded();
+    updater::update(
+        &patch_list,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &send_state,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    assert_eq!(installed_patch(&data_dir), 1003);
+    // shared folders are only read from
+    assert!(local_dir.join("1003.pwr").exists());
+
+    // patches the folder doesn't have can't be applied
+    let e = updater::local_patch_list(&local_dir, 1000).unwrap_err();
+    assert!(e.to_string().contains("1001.pwr is missing"));
+}
+
+#[test]
+fn long_install_paths_update() {
+    let server = MockServer::start();
+    let patch = server.patch(1101, b"patch", b"signature");
+    server.serve_patch_list(&[patch]);
+
+    let data_dir = common::test_dir("long-install-path-data");
+    let mut install_dir = common::test_dir("long-install-path-install");
+    while install_dir.as_os_str().len() <= applauncher::paths::MAX_PATH {
+        install_dir.push("a-rather-long-directory-name");
+    }
+    fs::create_dir_all(&install_dir).unwrap();
+    fs::write(install_dir.join("usc-game.exe"), b"game").unwrap();
+    let mut manifest = new_manifest(&install_dir, 1100);
+
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    assert_eq!(installed_patch(&data_dir), 1101);
+    assert!(install_dir.join("tmp-file-1101.pwr").exists());
+    // the backup has to make it past the limit as well
+    let backups_dir = backups::backups_dir(&data_dir, "unnamed-sdvx-clone");
+    assert!(backups_dir.join("1100").join("usc-game.exe").exists());
+}
+
+#[test]
+fn patches_only_come_from_allowed_hosts() {
+    let patch = |url: &str, sig: &str| PatchInfo {
+        id: 1201,
+        url: url.into(),
+        sig: sig.into(),
+        ..PatchInfo::default()
+    };
+    let settings = Settings {
+        update_server: "https://api.example.com".into(),
+        allowed_hosts: vec!["*.cdn.example.net".into()],
+        ..Settings::default()
+    };
+    let allowed_hosts = settings.allowed_hosts();
+
+    // payload on the CDN, signature from the API itself
+    let split = patch(
+        "https://eu.cdn.example.net/1201.pwr",
+        "https://API.example.com/1201.pwr.sig",
+    );
+    assert!(updater::check_hosts(&[split], &allowed_hosts).is_ok());
+
+    let elsewhere = patch(
+        "https://eu.cdn.example.net/1201.pwr",
+        "https://example.org/1201.pwr.sig",
+    );
+    assert!(updater::check_hosts(&[elsewhere], &allowed_hosts).is_err());
+
+    // a wildcard is for subdomains, and a lookalike isn't one of them
+    let bare = patch(
+        "https://cdn.example.net/1201.pwr",
+        "https://api.example.com/1201.pwr.sig",
+    );
+    assert!(updater::check_hosts(&[bare], &allowed_hosts).is_err());
+    let lookalike = patch(
+        "https://evilcdn.example.net/1201.pwr",
+        "https://api.example.com/1201.pwr.sig",
+    );
+    assert!(updater::check_hosts(&[lookalike], &allowed_hosts).is_err());
+}
+
+#[test]
+fn downloads_only_follow_redirects_to_allowed_hosts() {
+    let server = MockServer::start();
+    let patch = server.patch(1211, b"moved patch", b"signature");
+    let moved = format!("{}/moved/1211.pwr", server.url());
+    server.serve("/files/1211.pwr", 302, &[("Location", &moved)], b"");
+    server.serve("/moved/1211.pwr", 200, &[], b"moved patch");
+
+    let (send_state, _recv_state) = unbounded();
+    let weights = updater::task_weights(&[patch.size]);
+    let progress = updater::TaskProgress::new(send_state, &weights);
+    let settings = Settings {
+        update_server: server.url().into(),
+        ..Settings::default()
+    };
+    let cancel = CancelToken::default();
+
+    // the server moving a file around on itself is fine
+    let fetched =
+        updater::download_patch(&patch, weights[0], &progress, &settings, &cancel).unwrap();
+    assert_eq!(fs::read(&fetched.patch_file.path).unwrap(), b"moved patch");
+    drop(fetched);
+
+    server.serve(
+        "/files/1211.pwr",
+        302,
+        &[("Location", "http://downloads.example.org/1211.pwr")],
+        b"",
+    );
+    let e = updater::download_patch(&patch, weights[0], &progress, &settings, &cancel)
+        .err()
+        .unwrap();
+    assert!(e.to_string().contains("not allowed"));
+}
+
+#[test]
+fn interrupted_updates_resume_their_count() {
+    let server = MockServer::start();
+    let first = server.patch(1301, b"first patch", b"first signature");
+    let rejected = server.patch(1302, b"broken", b"second signature");
+    server.serve_patch_list(&[first, rejected]);
+
+    let data_dir = common::test_dir("resume-progress-data");
+    let install_dir = common::test_dir("resume-progress-install");
+    let mut manifest = new_manifest(&install_dir, 1300);
+
+    run_update(
+        &server,
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &CancelToken::default(),
+    )
+    .unwrap_err();
+    let saved = updater::load_progress(&data_dir).unwrap();
+    assert_eq!((saved.target, saved.applied, saved.total), (1302, 1, 2));
+
+    // the server only sends what's left once it knows about the first patch
+    let fixed = server.patch(1302, b"second patch", b"second signature");
+    let (send_state, recv_state) = unbounded();
+    updater::update(
+        &[fixed],
+        &mut manifest,
+        &data_dir,
+        &Settings::default(),
+        COPY_PATCHER,
+        &send_state,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    assert_eq!(installed_patch(&data_dir), 1302);
+    assert!(recv_state.try_iter().any(|worker_msg| matches!(
+        worker_msg,
+        WorkerMsg::Status(status) if status == "Resuming Update (1/2)..."
+    )));
+    assert!(updater::load_progress(&data_dir).is_none());
+}
+
+#[test]
+fn patches_for_another_game_are_refused() {
+    let server = MockServer::start();
+    let mut patch = server.patch(1401, b"patch", b"sig");
+    server.serve_patch_list(&[patch.clone()]);
+    assert_eq!(
+        updater::fetch_patch_list(&server.settings(), 0, None, None)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    patch.platform = "linux".into();
+    server.serve_patch_list(&[patch.clone()]);
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    assert!(e.to_string().contains("different game or platform"));
+
+    patch.platform = "win32".into();
+    patch.app = "another-game".into();
+    server.serve_patch_list(&[patch]);
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    assert!(e.to_string().contains("different game or platform"));
+}
+
+#[test]
+fn cancelling_abandons_a_request_in_flight() {
+    // takes the connection and never answers it
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let cancel = CancelToken::default();
+    thread::spawn({
+        let cancel = cancel.clone();
+        move || {
+            thread::sleep(Duration::from_millis(500));
+            cancel.cancel();
+        }
+    });
+
+    let started = Instant::now();
+    let e = updater::fetch_patch_list(
+        &Settings {
+            update_server: url,
+            ..Settings::default()
+        },
+        0,
+        None,
+        Some(&cancel),
+    )
+    .unwrap_err();
+    assert!(e.downcast_ref::<updater::Cancelled>().is_some());
+    assert!(started.elapsed() < Duration::from_secs(5));
+    drop(listener);
+}
+
+#[test]
+fn required_patches_go_first() {
+    let patch = |id: u64, requires: &[u64]| PatchInfo {
+        id,
+        requires: requires.to_vec(),
+        ..PatchInfo::default()
+    };
+    let ids = |patch_list: Vec<PatchInfo>| -> Vec<u64> {
+        patch_list.into_iter().map(|patch| patch.id).collect()
+    };
+
+    // nothing to reorder, and an installed requirement is already met
+    let in_order = [patch(11, &[10]), patch(12, &[11]), patch(13, &[])];
+    assert_eq!(
+        ids(updater::order_by_requirements(&in_order, 10).unwrap()),
+        vec![11, 12, 13]
+    );
+
+    // the base pack ships with a higher id than the patch that needs it
+    let base_pack_later = [patch(11, &[13]), patch(12, &[]), patch(13, &[])];
+    assert_eq!(
+        ids(updater::order_by_requirements(&base_pack_later, 10).unwrap()),
+        vec![12, 13, 11]
+    );
+}
+
+#[test]
+fn missing_requirements_are_refused() {
+    let patch = |id: u64, requires: &[u64]| PatchInfo {
+        id,
+        requires: requires.to_vec(),
+        ..PatchInfo::default()
+    };
+
+    let missing = [patch(11, &[]), patch(12, &[9, 20])];
+    let e = updater::order_by_requirements(&missing, 10).unwrap_err();
+    assert!(e.to_string().contains("needs patch 20"));
+
+    let each_other = [patch(11, &[12]), patch(12, &[11])];
+    let e = updater::order_by_requirements(&each_other, 10).unwrap_err();
+    assert!(e.to_string().contains("require each other"));
+}
+
+#[test]
+fn maintenance_is_told_apart_from_errors() {
+    let server = MockServer::start();
+    let fetch = || updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+
+    server.serve("/api/v0/patch", 503, &[("Retry-After", "120")], b"");
+    let e = fetch();
+    assert!(e
+        .downcast_ref::<updater::Maintenance>()
+        .unwrap()
+        .until
+        .is_some());
+
+    server.serve(
+        "/api/v0/patch",
+        200,
+        &[],
+        br#"{"maintenance": true, "until": "2026-10-15T12:00:00Z"}"#,
+    );
+    let e = fetch();
+    assert!(e
+        .downcast_ref::<updater::Maintenance>()
+        .unwrap()
+        .until
+        .is_some());
+    assert!(e.to_string().contains("under maintenance"));
+
+    // a server that's simply down isn't under maintenance
+    server.serve("/api/v0/patch", 503, &[], b"Service Unavailable");
+    assert!(fetch().downcast_ref::<updater::Maintenance>().is_none());
+}
+
+#[test]
+fn patch_list_path_follows_the_settings() {
+    let server = MockServer::start();
+    server.serve(
+        "/api/v1/patches",
+        200,
+        &[("Content-Type", "application/json")],
+        b"[]",
+    );
+    let request = PatchListRequest {
+        path: "/api/v1/patches".into(),
+        ..PatchListRequest::default()
+    };
+    updater::fetch_patch_list(
+        &Settings {
+            patch_list_request: request,
+            ..server.settings()
+        },
+        0,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(server.requests()[0].target, "/api/v1/patches");
+}
+
+#[test]
+fn duplicate_patches_are_listed_once() {
+    let server = MockServer::start();
+    let patch = server.patch(1901, b"patch", b"sig");
+    let next = server.patch(1902, b"next", b"sig");
+    server.serve_patch_list(&[patch.clone(), next.clone(), patch.clone()]);
+    let patch_list = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap();
+    let ids: Vec<u64> = patch_list.iter().map(|patch| patch.id).collect();
+    assert_eq!(ids, vec![1901, 1902]);
+
+    // the same id for another patch leaves no telling which one is meant
+    let mut impostor = next.clone();
+    impostor.id = 1901;
+    server.serve_patch_list(&[patch, next, impostor]);
+    let e = updater::fetch_patch_list(&server.settings(), 0, None, None).unwrap_err();
+    assert!(e.to_string().contains("two different patches"));
+}
+
+#[test]
+fn offline_machines_are_noticed_quickly() {
+    let server = MockServer::start();
+    assert!(updater::is_online(server.url()));
+
+    // nothing listens on the discard port, a server that's down isn't being offline though
+    assert!(updater::is_online("http://127.0.0.1:9"));
+    // a name that never resolves is what having no network looks like
+    assert!(!updater::is_online("http://applauncher.invalid"));
+    // a folder of patches needs no connection
+    assert!(updater::is_online("file:///patches"));
+}
+
+#[test]
+fn cleaning_up_leaves_other_games_downloads_alone() {
+    let own = paths::temp_path("cleanup-game");
+    let prefetched = paths::temp_path("cleanup-game-prefetched");
+    fs::create_dir_all(&own).unwrap();
+    fs::create_dir_all(&prefetched).unwrap();
+    fs::write(own.join("tmp-file-1971.pwr"), b"patch").unwrap();
+    fs::write(prefetched.join("tmp-file-1971.pwr"), b"patch").unwrap();
+
+    updater::cleanup_temp_files("Cleanup-Game");
+
+    assert!(!own.join("tmp-file-1971.pwr").exists());
+    assert!(prefetched.join("tmp-file-1971.pwr").exists());
+    let _ = fs::remove_dir_all(prefetched);
+}
+
+#[test]
+fn prefetched_patches_are_picked_up() {
+    let server = MockServer::start();
+    let patch_list = vec![server.patch(1951, b"prefetched patch", b"prefetched signature")];
+    server.serve_patch_list(&patch_list);
+
+    let data_dir = common::test_dir("prefetch-data");
+    let install_dir = common::test_dir("prefetch-install");
+    let mut manifest = new_manifest(&install_dir, 1950);
+    let settings = Settings {
+        update_server: server.url().into(),
+        ..Settings::default()
+    };
+
+    let cancel = CancelToken::default();
+    cancel.throttle(1024 * 1024);
+    let entry = manifest.games["unnamed-sdvx-clone"].clone();
+    updater::prefetch("unnamed-sdvx-clone", &entry, &data_dir, &settings, &cancel).unwrap();
+    let requests = server.requests().len();
+
+    let (send_state, _recv_state) = unbounded();
+    updater::update(
+        &patch_list,
+        &mut manifest,
+        &data_dir,
+        &settings,
+        COPY_PATCHER,
+        &send_state,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    assert_eq!(installed_patch(&data_dir), 1951);
+    // nothing was downloaded again
+    assert_eq!(server.requests().len(), requests);
+}
+
+#[test]
+fn updating_leaves_other_games_prefetches_alone() {
+    let server = MockServer::start();
+    let mut other_patch = server.patch(1971, b"other patch", b"other signature");
+    other_patch.app = "another-game".into();
+    server.serve_patch_list(&[other_patch]);
+    let own_patch = server.patch(1981, b"own patch", b"own signature");
+
+    let data_dir = common::test_dir("prefetch-others-data");
+    let install_dir = common::test_dir("prefetch-others-install");
+    let mut manifest = new_manifest(&install_dir, 1980);
+    let other_entry = AppEntry {
+        dir: common::test_dir("prefetch-others-other-install"),
+        patch: 1970,
+        ..AppEntry::default()
+    };
+    let settings = server.settings();
+
+    updater::prefetch(
+        "another-game",
+        &other_entry,
+        &data_dir,
+        &settings,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    let (send_state, _recv_state) = unbounded();
+    updater::update(
+        &[own_patch],
+        &mut manifest,
+        &data_dir,
+        &settings,
+        COPY_PATCHER,
+        &send_state,
+        &CancelToken::default(),
+    )
+    .unwrap();
+    assert_eq!(installed_patch(&data_dir), 1981);
+
+    // the other game's patch is still there, only its patch list is asked for again
+    let requests = server.requests().len();
+    updater::prefetch(
+        "another-game",
+        &other_entry,
+        &data_dir,
+        &settings,
+        &CancelToken::default(),
+    )
+    .unwrap();
+    assert_eq!(server.requests().len(), requests + 1);
+}
+
+#[test]
+fn prefetching_stops_at_the_version_lock() {
+    let server = MockServer::start();
+    let patch_list = vec![
+        server.patch(1991, b"locked patch", b"locked signature"),
+        server.patch(1992, b"later patch", b"later signature"),
+    ];
+    server.serve_patch_list(&patch_list);
+
+    let data_dir = common::test_dir("prefetch-locked-data");
+    let install_dir = common::test_dir("prefetch-locked-install");
+    let entry = new_manifest(&install_dir, 1990).games["unnamed-sdvx-clone"].clone();
+    let mut settings = server.settings();
+    settings
+        .games
+        .entry("unnamed-sdvx-clone".into())
+        .or_default()
+        .version_lock = Some(1991);
+
+    updater::prefetch(
+        "unnamed-sdvx-clone",
+        &entry,
+        &data_dir,
+        &settings,
+        &CancelToken::default(),
+    )
+    .unwrap();
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .any(|request| request.target == "/files/1991.pwr"));
+    assert!(!requests
+        .iter()
+        .any(|request| request.target.starts_with("/files/1992")));
+}
+
+#[test]
+fn downloads_are_hashed_every_time_they_arrive() {
+    let server = MockServer::start();
+    let patch = server.patch(1961, b"cached patch", b"cached signature");
+    server.serve(
+        "/files/1961.pwr",
+        200,
+        &[("ETag", "\"v1\"")],
+        b"cached patch",
+    );
+
+    let (send_state, _recv_state) = unbounded();
+    let weights = updater::task_weights(&[patch.size]);
+    let progress = updater::TaskProgress::new(send_state, &weights);
+    let cancel = CancelToken::default();
+    let settings = Settings {
+        download_buffer_kib: 8,
+        ..Settings::default()
+    };
+    let download = || updater::download_patch(&patch, weights[0], &progress, &settings, &cancel);
+    let verify = |fetched: &FetchedPatch| {
+        updater::verify(&patch, fetched, weights[0], &progress, &settings, &cancel)
+    };
+
+    let fetched = download().unwrap();
+    verify(&fetched).unwrap();
+    drop(fetched);
+
+    // the same file under the same ETag is still hashed, the corruption is caught and the
+    // file downloaded again
+    let fetched = download().unwrap();
+    fs::write(&fetched.patch_file.path, b"CACHED PATCH").unwrap();
+    let requests = server.requests().len();
+    verify(&fetched).unwrap();
+    assert_eq!(server.requests().len(), requests + 1);
+    assert_eq!(fs::read(&fetched.patch_file.path).unwrap(), b"cached patch");
+}
+
+#[test]
+fn html_error_pages_are_not_taken_for_patches() {
+    let server = MockServer::start();
+    let patch = server.patch(1981, b"patch", b"signature");
+    server.serve(
+        "/files/1981.pwr",
+        200,
+        &[("Content-Type", "text/html; charset=utf-8")],
+        b"<html><body>Bandwidth limit exceeded</body></html>",
+    );
+
+    let (send_state, _recv_state) = unbounded();
+    let weights = updater::task_weights(&[patch.size]);
+    let progress = updater::TaskProgress::new(send_state, &weights);
+    let e = updater::download_patch(
+        &patch,
+        weights[0],
+        &progress,
+        &Settings {
+            download_buffer_kib: 8,
+            ..Settings::default()
+        },
+        &CancelToken::default(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error::kind(&e), ErrorKind::Network);
+    assert!(e.to_string().contains("web page"));
+    // asked for again before giving up
+    assert!(server.requests().len() > 1);
+}